@@ -27,11 +27,8 @@ async fn do_main(args: Cli) -> std::result::Result<(), gpio_cdev::Error> {
         "gpioevents",
     )?)?;
 
-    loop {
-        match events.next().await {
-            Some(event) => println!("{:?}", event?),
-            None => break,
-        };
+    while let Some(event) = events.next().await {
+        println!("{:?}", event?);
     }
 
     Ok(())
@@ -0,0 +1,50 @@
+// Copyright (c) 2018 The rust-gpio-cdev Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Clone of the basic functionality of libgpiod's gpioget
+
+use gpio_cdev::{parse_offsets, Chip, LineRequestFlags};
+use quicli::prelude::*;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+struct Cli {
+    /// Request flags, e.g. "input,pull-up,active-low"
+    #[structopt(short = "f", long = "flags", default_value = "input")]
+    flags: LineRequestFlags,
+    /// The gpiochip device (e.g. /dev/gpiochip0)
+    chip: String,
+    /// The offsets of the GPIO lines to read, e.g. "2-7,20"
+    lines: String,
+}
+
+// Use like:
+//   gpioget -f input,pull-down /dev/gpiochip0 0-3,7
+//
+fn do_main(args: Cli) -> std::result::Result<(), gpio_cdev::Error> {
+    let mut chip = Chip::new(args.chip)?;
+    let lines = chip.get_lines(parse_offsets(&args.lines)?)?;
+    let defaults = vec![0; lines.len()];
+    let handle = lines.request(args.flags, &defaults, "gpioget")?;
+    let values: Vec<String> = handle
+        .get_values()?
+        .iter()
+        .map(|value| value.to_string())
+        .collect();
+    println!("{}", values.join(" "));
+
+    Ok(())
+}
+
+fn main() -> CliResult {
+    let args = Cli::from_args();
+    do_main(args).or_else(|e| {
+        error!("{}", e);
+        Ok(())
+    })
+}
@@ -0,0 +1,74 @@
+// Copyright (c) 2018 The rust-gpio-cdev Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Clone of the basic functionality of libgpiod's gpioset
+
+use gpio_cdev::{Chip, LineRequestFlags};
+use quicli::prelude::*;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+struct Cli {
+    /// Request flags, e.g. "output,open-drain"
+    #[structopt(short = "f", long = "flags", default_value = "output")]
+    flags: LineRequestFlags,
+    /// The gpiochip device (e.g. /dev/gpiochip0)
+    chip: String,
+    /// The offset and value of each GPIO line in the form "off=<0|1>"
+    line_values: Vec<String>,
+}
+
+// Use like:
+//   gpioset -f output,active-low /dev/gpiochip0 3=1 4=0
+//
+// The lines are held at the given values until Enter is pressed.
+//
+fn do_main(args: Cli) -> std::result::Result<(), gpio_cdev::Error> {
+    let mut chip = Chip::new(args.chip)?;
+    let mut offsets = Vec::new();
+    let mut values = Vec::new();
+
+    for arg in &args.line_values {
+        let mut lv = arg.splitn(2, '=');
+        match (
+            lv.next().and_then(|o| o.parse::<u32>().ok()),
+            lv.next().and_then(|v| v.parse::<u8>().ok()),
+        ) {
+            (Some(offset), Some(value)) => {
+                offsets.push(offset);
+                values.push(value);
+            }
+            _ => {
+                eprintln!("Expected <offset>=<value>, got {:?}", arg);
+                return Ok(());
+            }
+        }
+    }
+
+    // The values are set as part of the request
+    let handle = chip
+        .get_lines(&offsets)?
+        .request(args.flags, &values, "gpioset")?;
+    println!(
+        "Driving lines {} ({})... Enter to exit",
+        handle.lines(),
+        args.flags
+    );
+    let mut buf = String::new();
+    ::std::io::stdin().read_line(&mut buf)?;
+
+    Ok(())
+}
+
+fn main() -> CliResult {
+    let args = Cli::from_args();
+    do_main(args).or_else(|e| {
+        error!("{}", e);
+        Ok(())
+    })
+}
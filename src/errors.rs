@@ -2,7 +2,7 @@
 //!
 //! In futures versions of the crate, this module will no longer be included in the crate.
 
-use crate::IoctlKind;
+use crate::{IoctlKind, LineFlags, LineRequestFlags};
 use std::error::Error as StdError;
 use std::fmt;
 use std::io::Error as IOError;
@@ -14,13 +14,40 @@ pub struct Error {
     kind: ErrorKind,
 }
 
+/// What went wrong, as returned by [`Error::kind`]
+///
+/// New kinds of error may be added in any release, so matches on this
+/// must include a wildcard arm.
+///
+/// [`Error::kind`]: struct.Error.html#method.kind
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ErrorKind {
     Event(nix::Error),
     Io(IOError),
-    Ioctl { kind: IoctlKind, cause: nix::Error },
+    Ioctl {
+        kind: IoctlKind,
+        cause: nix::Error,
+    },
     InvalidRequest(usize, usize),
+    TooManyLines(usize),
+    NoLines,
     Offset(u32),
+    UnknownLine(String),
+    DuplicateName(String),
+    ConsumerTooLong(usize),
+    InvalidOffsets(String),
+    UnrepresentableFlags(LineFlags),
+    OutputProbe(LineRequestFlags),
+    ConflictingFlags(LineRequestFlags),
+    MissingFlags {
+        flags: LineRequestFlags,
+        requires: LineRequestFlags,
+    },
+    UnknownToken {
+        token: String,
+        expected: Vec<&'static str>,
+    },
 }
 
 pub(crate) fn ioctl_err(kind: IoctlKind, cause: nix::Error) -> Error {
@@ -35,27 +62,131 @@ pub(crate) fn invalid_err(n_lines: usize, n_values: usize) -> Error {
     }
 }
 
+pub(crate) fn too_many_lines_err(n_lines: usize) -> Error {
+    Error {
+        kind: ErrorKind::TooManyLines(n_lines),
+    }
+}
+
+pub(crate) fn no_lines_err() -> Error {
+    Error {
+        kind: ErrorKind::NoLines,
+    }
+}
+
 pub(crate) fn offset_err(offset: u32) -> Error {
     Error {
         kind: ErrorKind::Offset(offset),
     }
 }
 
+pub(crate) fn unknown_line_err(name: &str) -> Error {
+    Error {
+        kind: ErrorKind::UnknownLine(name.to_owned()),
+    }
+}
+
+pub(crate) fn duplicate_name_err(name: String) -> Error {
+    Error {
+        kind: ErrorKind::DuplicateName(name),
+    }
+}
+
+pub(crate) fn consumer_too_long_err(len: usize) -> Error {
+    Error {
+        kind: ErrorKind::ConsumerTooLong(len),
+    }
+}
+
+pub(crate) fn invalid_offsets_err(reason: String) -> Error {
+    Error {
+        kind: ErrorKind::InvalidOffsets(reason),
+    }
+}
+
+pub(crate) fn unrepresentable_err(flags: LineFlags) -> Error {
+    Error {
+        kind: ErrorKind::UnrepresentableFlags(flags),
+    }
+}
+
+pub(crate) fn output_probe_err(flags: LineRequestFlags) -> Error {
+    Error {
+        kind: ErrorKind::OutputProbe(flags),
+    }
+}
+
+pub(crate) fn conflicting_flags_err(flags: LineRequestFlags) -> Error {
+    Error {
+        kind: ErrorKind::ConflictingFlags(flags),
+    }
+}
+
+pub(crate) fn missing_flags_err(flags: LineRequestFlags, requires: LineRequestFlags) -> Error {
+    Error {
+        kind: ErrorKind::MissingFlags { flags, requires },
+    }
+}
+
+pub(crate) fn unknown_token_err(token: &str, expected: Vec<&'static str>) -> Error {
+    Error {
+        kind: ErrorKind::UnknownToken {
+            token: token.to_owned(),
+            expected,
+        },
+    }
+}
+
 pub(crate) fn event_err(err: nix::Error) -> Error {
     Error {
         kind: ErrorKind::Event(err),
     }
 }
 
+impl Error {
+    /// What went wrong
+    ///
+    /// For failed ioctls this identifies which ioctl was issued and the
+    /// errno returned by the kernel.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// True if the kernel rejected an ioctl's arguments as invalid
+    pub(crate) fn is_invalid(&self) -> bool {
+        matches!(
+            self.kind,
+            ErrorKind::Ioctl {
+                cause: nix::errno::Errno::EINVAL,
+                ..
+            }
+        )
+    }
+
+    /// True if the kernel rejected an ioctl because the line is already in use
+    pub(crate) fn is_busy(&self) -> bool {
+        matches!(
+            self.kind,
+            ErrorKind::Ioctl {
+                cause: nix::errno::Errno::EBUSY,
+                ..
+            }
+        )
+    }
+}
+
 impl fmt::Display for IoctlKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             IoctlKind::ChipInfo => write!(f, "get chip info"),
             IoctlKind::LineInfo => write!(f, "get line info"),
             IoctlKind::LineHandle => write!(f, "get line handle"),
-            IoctlKind::LineEvent => write!(f, "get line event "),
+            IoctlKind::LineEvent => write!(f, "get line event"),
             IoctlKind::GetLine => write!(f, "get line value"),
             IoctlKind::SetLine => write!(f, "set line value"),
+            IoctlKind::SetConfig => write!(f, "set line config"),
+            IoctlKind::WatchLineInfo => write!(f, "watch line info"),
+            IoctlKind::UnwatchLineInfo => write!(f, "unwatch line info"),
         }
     }
 }
@@ -71,7 +202,48 @@ impl fmt::Display for Error {
                 "Invalid request: {} values requested to be set but only {} lines are open",
                 n_values, n_lines
             ),
+            ErrorKind::TooManyLines(n_lines) => write!(
+                f,
+                "Invalid request: {} lines requested but at most {} can be requested together",
+                n_lines,
+                crate::ffi::GPIOHANDLES_MAX
+            ),
+            ErrorKind::NoLines => write!(f, "Invalid request: at least one line must be requested"),
             ErrorKind::Offset(offset) => write!(f, "Offset {} is out of range", offset),
+            ErrorKind::InvalidOffsets(reason) => write!(f, "Invalid list of offsets: {}", reason),
+            ErrorKind::UnknownLine(name) => write!(f, "No line named {:?}", name),
+            ErrorKind::DuplicateName(name) => {
+                write!(f, "Name {:?} is given to several lines", name)
+            }
+            ErrorKind::ConsumerTooLong(len) => {
+                write!(f, "Consumer label is {} bytes but at most 31 are kept", len)
+            }
+            ErrorKind::UnrepresentableFlags(flags) => write!(
+                f,
+                "Line flags {:?} cannot be expressed as request flags",
+                flags
+            ),
+            ErrorKind::OutputProbe(flags) => write!(
+                f,
+                "Cannot probe support for flags {:?}, as the request would drive a line",
+                flags
+            ),
+            ErrorKind::ConflictingFlags(flags) => write!(
+                f,
+                "Invalid request: flags {:?} cannot be requested together",
+                flags
+            ),
+            ErrorKind::MissingFlags { flags, requires } => write!(
+                f,
+                "Invalid request: flags {:?} require one of {:?}",
+                flags, requires
+            ),
+            ErrorKind::UnknownToken { token, expected } => write!(
+                f,
+                "Unrecognized option {:?}, expected one of: {}",
+                token,
+                expected.join(", ")
+            ),
         }
     }
 }
@@ -6,7 +6,18 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::IoctlKind;
+//! Raw definitions of the kernel GPIO character device uAPI
+//!
+//! These mirror the structs and ioctls in `include/uapi/linux/gpio.h`.
+//! With the `unstable-uapi` feature enabled this module is public so that
+//! ioctls not yet wrapped by the high-level API can be issued directly.
+//! Its contents are **not** covered by semver and may change in any
+//! release.
+
+use crate::{rstr_lcpy, IoctlKind, LineRequestFlags};
+use std::cmp::min;
+use std::fmt;
+use std::mem;
 
 pub const GPIOHANDLES_MAX: usize = 64;
 
@@ -26,6 +37,31 @@ pub struct gpioline_info {
     pub consumer: [libc::c_char; 32],
 }
 
+pub const GPIOLINE_CHANGED_REQUESTED: u32 = 1;
+pub const GPIOLINE_CHANGED_RELEASED: u32 = 2;
+pub const GPIOLINE_CHANGED_CONFIG: u32 = 3;
+
+#[repr(C)]
+pub struct gpioline_info_changed {
+    pub info: gpioline_info,
+    pub timestamp: u64,
+    pub event_type: u32,
+    pub padding: [u32; 5],
+}
+
+impl gpioline_info_changed {
+    /// Decode a record read from the chip fd
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` is shorter than `struct gpioline_info_changed`.
+    pub fn from_bytes(buf: &[u8]) -> Self {
+        assert!(buf.len() >= mem::size_of::<Self>());
+        // Every field is a plain integer, so any bit pattern is valid.
+        unsafe { (buf.as_ptr() as *const Self).read_unaligned() }
+    }
+}
+
 #[repr(C)]
 pub struct gpiohandle_request {
     pub lineoffsets: [u32; GPIOHANDLES_MAX],
@@ -36,6 +72,76 @@ pub struct gpiohandle_request {
     pub fd: libc::c_int,
 }
 
+impl gpiohandle_request {
+    /// Build a request for `offsets` on a chip, ready to pass to
+    /// [`gpio_get_linehandle_ioctl`]
+    ///
+    /// `default_values` gives the output value of each line by position
+    /// and may be shorter than `offsets`, in which case the remaining lines
+    /// default to 0.  The `consumer` label is truncated to 31 bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than `GPIOHANDLES_MAX` offsets are given or if there
+    /// are more `default_values` than `offsets`.
+    pub fn new(
+        offsets: &[u32],
+        flags: LineRequestFlags,
+        default_values: &[u8],
+        consumer: &str,
+    ) -> Self {
+        assert!(offsets.len() <= GPIOHANDLES_MAX);
+        assert!(default_values.len() <= offsets.len());
+        let mut request = gpiohandle_request {
+            lineoffsets: [0; GPIOHANDLES_MAX],
+            flags: flags.bits(),
+            default_values: [0; GPIOHANDLES_MAX],
+            consumer_label: unsafe { mem::zeroed() },
+            lines: offsets.len() as u32,
+            fd: 0,
+        };
+        request.lineoffsets[..offsets.len()].copy_from_slice(offsets);
+        request.default_values[..default_values.len()].copy_from_slice(default_values);
+        unsafe {
+            rstr_lcpy(
+                request.consumer_label[..].as_mut_ptr(),
+                consumer,
+                request.consumer_label.len(),
+            );
+        }
+        request
+    }
+}
+
+impl fmt::Debug for gpiohandle_request {
+    /// Shows only the fields in use, e.g. the offsets actually requested
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let n = min(self.lines as usize, GPIOHANDLES_MAX);
+        f.debug_struct("gpiohandle_request")
+            .field("lineoffsets", &&self.lineoffsets[..n])
+            .field("flags", &LineRequestFlags::from_bits_truncate(self.flags))
+            .field("default_values", &&self.default_values[..n])
+            .field("consumer_label", &label_lossy(&self.consumer_label))
+            .field("lines", &self.lines)
+            .field("fd", &self.fd)
+            .finish()
+    }
+}
+
+/// Decode a label up to its NUL, or the whole array if it has none
+fn label_lossy(label: &[libc::c_char]) -> String {
+    let bytes: Vec<u8> = label.iter().map(|&c| c as u8).collect();
+    let len = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).into_owned()
+}
+
+#[repr(C)]
+pub struct gpiohandle_config {
+    pub flags: u32,
+    pub default_values: [u8; GPIOHANDLES_MAX],
+    pub padding: [u32; 4],
+}
+
 #[repr(C)]
 pub struct gpiohandle_data {
     pub values: [u8; GPIOHANDLES_MAX],
@@ -51,20 +157,114 @@ pub struct gpioevent_request {
 }
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct gpioevent_data {
     pub timestamp: u64,
     pub id: u32,
 }
 
+/// Size of `struct gpioevent_data` where `u64` is 4-byte aligned (e.g. i386)
+pub const GPIOEVENT_DATA_SIZE_PACKED: usize = 12;
+/// Size of `struct gpioevent_data` where `u64` is 8-byte aligned
+pub const GPIOEVENT_DATA_SIZE_MAX: usize = 16;
+
+impl gpioevent_data {
+    /// Decode a record of either layout; the fields sit at the same offsets
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` is shorter than `GPIOEVENT_DATA_SIZE_PACKED`.
+    pub fn from_bytes(buf: &[u8]) -> Self {
+        let mut timestamp = [0u8; 8];
+        let mut id = [0u8; 4];
+        timestamp.copy_from_slice(&buf[0..8]);
+        id.copy_from_slice(&buf[8..12]);
+        gpioevent_data {
+            timestamp: u64::from_ne_bytes(timestamp),
+            id: u32::from_ne_bytes(id),
+        }
+    }
+
+    /// Decode one whole record as returned by `read()`
+    ///
+    /// Returns `None` unless `buf` is exactly the size of one of the two
+    /// layouts, as the kernel never returns partial records.
+    pub fn from_record(buf: &[u8]) -> Option<Self> {
+        match buf.len() {
+            GPIOEVENT_DATA_SIZE_PACKED | GPIOEVENT_DATA_SIZE_MAX => Some(Self::from_bytes(buf)),
+            _ => None,
+        }
+    }
+}
+
+// None of the v1 structs are packed in the kernel headers, so their layout
+// follows the C ABI of each target.  Only `gpioevent_data` differs between
+// targets, as its trailing u64 alignment padding is absent where u64 is
+// 4-byte aligned (32-bit x86); see `LineEventHandle::read_event`.  These
+// checks fail the build on any target where the layout diverges from the
+// kernel's.
+const _: [(); 68] = [(); mem::size_of::<gpiochip_info>()];
+const _: [(); 72] = [(); mem::size_of::<gpioline_info>()];
+const _: [(); 104] = [(); mem::size_of::<gpioline_info_changed>()];
+const _: [(); 364] = [(); mem::size_of::<gpiohandle_request>()];
+const _: [(); 84] = [(); mem::size_of::<gpiohandle_config>()];
+const _: [(); 64] = [(); mem::size_of::<gpiohandle_data>()];
+const _: [(); 48] = [(); mem::size_of::<gpioevent_request>()];
+#[cfg(target_arch = "x86")]
+const _: [(); GPIOEVENT_DATA_SIZE_PACKED] = [(); mem::size_of::<gpioevent_data>()];
+#[cfg(not(target_arch = "x86"))]
+const _: [(); GPIOEVENT_DATA_SIZE_MAX] = [(); mem::size_of::<gpioevent_data>()];
+
+// Alignment decides where the u64 fields start, and so the offsets of the
+// fields after them; the structs without u64 fields are 4-byte aligned.
+const _: [(); 4] = [(); mem::align_of::<gpioline_info>()];
+const _: [(); 4] = [(); mem::align_of::<gpiohandle_request>()];
+const _: [(); 4] = [(); mem::align_of::<gpiohandle_config>()];
+const _: [(); 4] = [(); mem::align_of::<gpioevent_request>()];
+const _: [(); mem::align_of::<u64>()] = [(); mem::align_of::<gpioline_info_changed>()];
+const _: [(); mem::align_of::<u64>()] = [(); mem::align_of::<gpioevent_data>()];
+
+// The request number each wrapper issues, kept so tests can check it
+// against the kernel's encoding.
+#[cfg(test)]
+macro_rules! request_code {
+    (ioctl_read, $ioty:expr, $nr:expr, $ty:ty) => {
+        nix::request_code_read!($ioty, $nr, mem::size_of::<$ty>())
+    };
+    (ioctl_readwrite, $ioty:expr, $nr:expr, $ty:ty) => {
+        nix::request_code_readwrite!($ioty, $nr, mem::size_of::<$ty>())
+    };
+}
+
+// Every ioctl goes through a wrapper generated here, which retries calls
+// interrupted by a signal and tags failures with the `IoctlKind` issued.
 macro_rules! wrap_ioctl {
-    ($ioctl_macro:ident!($name:ident, $ioty:expr, $nr:expr, $ty:ident), $ioctl_error_type:expr) => {
+    ($ioctl_macro:ident!($name:ident, $ioty:expr, $nr:expr, $ty:ty), $ioctl_error_type:expr) => {
         mod $name {
-            $ioctl_macro!($name, $ioty, $nr, super::$ty);
+            #[allow(unused_imports)]
+            use super::*;
+            $ioctl_macro!($name, $ioty, $nr, $ty);
+
+            #[cfg(test)]
+            #[allow(dead_code)]
+            pub(super) const REQUEST: nix::sys::ioctl::ioctl_num_type =
+                request_code!($ioctl_macro, $ioty, $nr, $ty);
         }
 
-        pub(crate) fn $name(fd: libc::c_int, data: &mut $ty) -> crate::errors::Result<libc::c_int> {
-            unsafe {
-                $name::$name(fd, data).map_err(|e| crate::errors::ioctl_err($ioctl_error_type, e))
+        /// Issue the ioctl, retrying if interrupted by a signal
+        ///
+        /// # Safety
+        ///
+        /// `fd` must be a GPIO character device or a handle obtained from
+        /// one that accepts this ioctl.  Other drivers may interpret the
+        /// same request number differently, e.g. writing through `data`
+        /// as a different type.
+        pub unsafe fn $name(fd: libc::c_int, data: &mut $ty) -> crate::errors::Result<libc::c_int> {
+            loop {
+                match $name::$name(fd, data) {
+                    Err(nix::errno::Errno::EINTR) => continue,
+                    res => return res.map_err(|e| crate::errors::ioctl_err($ioctl_error_type, e)),
+                }
             }
         }
     };
@@ -86,6 +286,14 @@ wrap_ioctl!(
     ioctl_readwrite!(gpio_get_lineevent_ioctl, 0xB4, 0x04, gpioevent_request),
     IoctlKind::LineEvent
 );
+wrap_ioctl!(
+    ioctl_readwrite!(gpio_get_lineinfo_watch_ioctl, 0xB4, 0x0B, gpioline_info),
+    IoctlKind::WatchLineInfo
+);
+wrap_ioctl!(
+    ioctl_readwrite!(gpio_get_lineinfo_unwatch_ioctl, 0xB4, 0x0C, u32),
+    IoctlKind::UnwatchLineInfo
+);
 
 wrap_ioctl!(
     ioctl_readwrite!(
@@ -105,3 +313,106 @@ wrap_ioctl!(
     ),
     IoctlKind::SetLine
 );
+wrap_ioctl!(
+    ioctl_readwrite!(gpiohandle_set_config_ioctl, 0xB4, 0x0A, gpiohandle_config),
+    IoctlKind::SetConfig
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: u64, id: u32, size: usize) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size);
+        buf.extend_from_slice(&timestamp.to_ne_bytes());
+        buf.extend_from_slice(&id.to_ne_bytes());
+        buf.resize(size, 0xaa);
+        buf
+    }
+
+    #[test]
+    fn request_offsets_and_flags() {
+        let flags = LineRequestFlags::OUTPUT | LineRequestFlags::ACTIVE_LOW;
+        let request = gpiohandle_request::new(&[4, 17, 27], flags, &[1, 0, 1], "test");
+        assert_eq!(request.lines, 3);
+        assert_eq!(&request.lineoffsets[..3], &[4, 17, 27]);
+        assert!(request.lineoffsets[3..].iter().all(|&o| o == 0));
+        assert_eq!(request.flags, flags.bits());
+        assert_eq!(&request.default_values[..3], &[1, 0, 1]);
+        assert_eq!(request.fd, 0);
+    }
+
+    #[test]
+    fn request_pads_default_values() {
+        let request = gpiohandle_request::new(&[4, 17, 27], LineRequestFlags::OUTPUT, &[1], "test");
+        assert_eq!(&request.default_values[..3], &[1, 0, 0]);
+        assert!(request.default_values.iter().skip(1).all(|&v| v == 0));
+    }
+
+    #[test]
+    fn request_truncates_consumer_label() {
+        let long = "x".repeat(40);
+        let request = gpiohandle_request::new(&[4], LineRequestFlags::INPUT, &[], &long);
+        let label = &request.consumer_label;
+        assert!(label[..31].iter().all(|&c| c == b'x' as libc::c_char));
+        assert_eq!(label[31], 0);
+
+        let request = gpiohandle_request::new(&[4], LineRequestFlags::INPUT, &[], "probe");
+        assert_eq!(label_lossy(&request.consumer_label), "probe");
+        assert!(request.consumer_label[5..].iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn debug_label_without_nul() {
+        let mut request = gpiohandle_request::new(&[3], LineRequestFlags::INPUT, &[], "");
+        for c in request.consumer_label.iter_mut() {
+            *c = b'x' as libc::c_char;
+        }
+        let debug = format!("{:?}", request);
+        assert!(debug.contains(&format!("consumer_label: \"{}\"", "x".repeat(32))));
+    }
+
+    #[test]
+    fn lineinfo_unwatch_request_code() {
+        // _IOWR(0xB4, 0x0C, __u32) in include/uapi/linux/gpio.h
+        assert_eq!(
+            gpio_get_lineinfo_unwatch_ioctl::REQUEST,
+            nix::request_code_readwrite!(0xB4, 0x0C, 4)
+        );
+        #[cfg(any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64"
+        ))]
+        assert_eq!(gpio_get_lineinfo_unwatch_ioctl::REQUEST as u32, 0xC004_B40C);
+    }
+
+    #[test]
+    fn decodes_packed_record() {
+        let buf = record(0x0102_0304_0506_0708, 1, GPIOEVENT_DATA_SIZE_PACKED);
+        let data = gpioevent_data::from_record(&buf).unwrap();
+        assert_eq!(data.timestamp, 0x0102_0304_0506_0708);
+        assert_eq!(data.id, 1);
+    }
+
+    #[test]
+    fn decodes_padded_record() {
+        let buf = record(1_595_421_346_405_811_712, 2, GPIOEVENT_DATA_SIZE_MAX);
+        let data = gpioevent_data::from_record(&buf).unwrap();
+        assert_eq!(data.timestamp, 1_595_421_346_405_811_712);
+        assert_eq!(data.id, 2);
+    }
+
+    #[test]
+    fn rejects_other_record_sizes() {
+        for &size in &[0, 1, 8, 11, 13, 15, 17, 24, 32] {
+            let buf = record(42, 1, size.max(12));
+            assert!(
+                gpioevent_data::from_record(&buf[..size]).is_none(),
+                "accepted a {} byte record",
+                size
+            );
+        }
+    }
+}
@@ -90,24 +90,33 @@ extern crate bitflags;
 extern crate nix;
 
 use std::cmp::min;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::ffi::CStr;
 use std::fs::{read_dir, File, ReadDir};
 use std::io::Read;
 use std::mem;
-use std::ops::Index;
-use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::ops::{BitAnd, BitOr, Index, Sub};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::path::{Path, PathBuf};
 use std::ptr;
 use std::slice;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "async-tokio")]
 #[cfg_attr(docsrs, doc(cfg(feature = "async-tokio")))]
 mod async_tokio;
 pub mod errors; // pub portion is deprecated
+#[cfg(feature = "unstable-uapi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "unstable-uapi")))]
+pub mod ffi;
+#[cfg(not(feature = "unstable-uapi"))]
 mod ffi;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum IoctlKind {
     ChipInfo,
     LineInfo,
@@ -115,6 +124,9 @@ pub enum IoctlKind {
     LineEvent,
     GetLine,
     SetLine,
+    SetConfig,
+    WatchLineInfo,
+    UnwatchLineInfo,
 }
 
 #[cfg(feature = "async-tokio")]
@@ -128,6 +140,9 @@ unsafe fn rstr_lcpy(dst: *mut libc::c_char, src: &str, length: usize) {
     slice::from_raw_parts_mut(dst, length)[copylen - 1] = 0;
 }
 
+/// Longest consumer label the kernel stores without truncating it
+pub(crate) const CONSUMER_LABEL_MAX: usize = 31;
+
 #[derive(Debug)]
 struct InnerChip {
     pub path: PathBuf,
@@ -135,6 +150,7 @@ struct InnerChip {
     pub name: String,
     pub label: String,
     pub lines: u32,
+    pub supported_flags: Mutex<HashMap<u32, bool>>,
 }
 
 /// A GPIO Chip maps to the actual device driver instance in hardware that
@@ -203,12 +219,119 @@ pub fn chips() -> Result<ChipIterator> {
     })
 }
 
+/// Find the line named `name` on any GPIO chip on this system
+///
+/// Chips are searched in the order returned by [`chips()`] and lines in
+/// offset order, returning the chip and offset of the first line whose
+/// name matches exactly.  Line names are set by the drivers or device
+/// tree and are not guaranteed to be unique; if several lines share the
+/// name only the first found is returned, and which one that is may
+/// depend on the order the chips were enumerated in.
+///
+/// Chips which cannot be opened, e.g. for lack of permission, are
+/// skipped.  The error from opening a chip is only returned if no chip
+/// could be searched at all.
+///
+/// ```no_run
+/// # fn main() -> Result<(), gpio_cdev::Error> {
+/// if let Some((mut chip, offset)) = gpio_cdev::find_line_by_name("PA07")? {
+///     let line = chip.get_line(offset)?;
+///     println!("{}", line.info()?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`chips()`]: fn.chips.html
+pub fn find_line_by_name(name: &str) -> Result<Option<(Chip, u32)>> {
+    let mut searched = false;
+    let mut open_err = None;
+    for chip in chips()? {
+        let chip = match chip {
+            Ok(chip) => chip,
+            Err(e) => {
+                open_err.get_or_insert(e);
+                continue;
+            }
+        };
+        searched = true;
+        if let Some(line) = chip.lines_filtered(|info| info.name() == Some(name)).next() {
+            let (offset, _) = line?;
+            return Ok(Some((chip, offset)));
+        }
+    }
+    match open_err {
+        Some(e) if !searched => Err(e),
+        _ => Ok(None),
+    }
+}
+
+/// Parse a list of offsets such as `2-7,20`
+///
+/// The list is comma-separated, and each entry is an offset or an
+/// inclusive range of offsets `first-last`.  Offsets are returned in the
+/// order given, ready to pass to [`Chip::get_lines`].  This is the syntax
+/// produced by the `Display` impl of [`Lines`].
+///
+/// ```
+/// assert_eq!(gpio_cdev::parse_offsets("2-5,20").unwrap(), vec![2, 3, 4, 5, 20]);
+/// assert!(gpio_cdev::parse_offsets("2-5,4").is_err());
+/// ```
+///
+/// # Errors
+///
+/// Fails with [`ErrorKind::InvalidOffsets`] if an entry is malformed, a
+/// range is reversed or an offset appears more than once, and with
+/// [`ErrorKind::TooManyLines`] for more than 64 offsets.
+///
+/// [`Chip::get_lines`]: struct.Chip.html#method.get_lines
+/// [`Lines`]: struct.Lines.html
+/// [`ErrorKind::InvalidOffsets`]: errors/enum.ErrorKind.html#variant.InvalidOffsets
+/// [`ErrorKind::TooManyLines`]: errors/enum.ErrorKind.html#variant.TooManyLines
+pub fn parse_offsets(s: &str) -> Result<Vec<u32>> {
+    let parse = |t: &str| {
+        t.trim()
+            .parse::<u32>()
+            .map_err(|_| invalid_offsets_err(format!("{:?} is not an offset", t)))
+    };
+    let mut offsets = Vec::new();
+    for entry in s.split(',').filter(|e| !e.trim().is_empty()) {
+        let (first, last) = match entry.find('-') {
+            Some(i) => (parse(&entry[..i])?, parse(&entry[i + 1..])?),
+            None => (parse(entry)?, parse(entry)?),
+        };
+        if first > last {
+            return Err(invalid_offsets_err(format!("{:?} is reversed", entry)));
+        }
+        for offset in first..=last {
+            if offsets.contains(&offset) {
+                return Err(invalid_offsets_err(format!(
+                    "offset {} is given more than once",
+                    offset
+                )));
+            }
+            if offsets.len() == ffi::GPIOHANDLES_MAX {
+                return Err(too_many_lines_err(
+                    offsets.len() + (last - offset) as usize + 1,
+                ));
+            }
+            offsets.push(offset);
+        }
+    }
+    Ok(offsets)
+}
+
 impl Chip {
     /// Open the GPIO Chip at the provided path (e.g. `/dev/gpiochip<N>`)
+    ///
+    /// The device is opened read-only.  The kernel does not require write
+    /// access to the chip for any operation, including requesting outputs
+    /// and setting their values, so read permission on the device node is
+    /// all that is needed.
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
         let f = File::open(path.as_ref())?;
         let mut info: ffi::gpiochip_info = unsafe { mem::zeroed() };
-        ffi::gpio_get_chipinfo_ioctl(f.as_raw_fd(), &mut info)?;
+        unsafe { ffi::gpio_get_chipinfo_ioctl(f.as_raw_fd(), &mut info) }?;
 
         Ok(Self {
             inner: Arc::new(InnerChip {
@@ -225,6 +348,7 @@ impl Chip {
                         .into_owned()
                 },
                 lines: info.lines,
+                supported_flags: Mutex::new(HashMap::new()),
             }),
         })
     }
@@ -234,6 +358,28 @@ impl Chip {
         self.inner.path.as_path()
     }
 
+    /// The major and minor device numbers of this chip's character device
+    ///
+    /// These identify the chip independently of the path it was opened
+    /// with, and can be used to find it in sysfs under
+    /// `/sys/dev/char/MAJOR:MINOR` or to match it against udev devices.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), gpio_cdev::Error> {
+    /// let chip = gpio_cdev::Chip::new("/dev/gpiochip0")?;
+    /// let (major, minor) = chip.device_number()?;
+    /// println!("/sys/dev/char/{}:{}", major, minor);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn device_number(&self) -> Result<(u32, u32)> {
+        let rdev = self.inner.file.metadata()?.rdev();
+        Ok((
+            nix::sys::stat::major(rdev) as u32,
+            nix::sys::stat::minor(rdev) as u32,
+        ))
+    }
+
     /// The name of the device driving this GPIO chip in the kernel
     pub fn name(&self) -> &str {
         self.inner.name.as_str()
@@ -265,23 +411,403 @@ impl Chip {
     /// are several banks of GPIOs with each bank containing 32
     /// GPIOs.  For this hardware and driver something like
     /// `GPIO2_5` would map to offset 37.
-    pub fn get_line(&mut self, offset: u32) -> Result<Line> {
-        Line::new(self.inner.clone(), offset)
+    pub fn get_line<O: Into<LineOffset>>(&mut self, offset: O) -> Result<Line> {
+        Line::new(self.inner.clone(), offset.into().0)
     }
 
     /// Get a handle to multiple GPIO line at a given offsets
     ///
-    /// The group of lines can be manipulated simultaneously.
-    pub fn get_lines(&mut self, offsets: &[u32]) -> Result<Lines> {
+    /// The group of lines can be manipulated simultaneously.  The kernel
+    /// allows at most 64 lines to be requested together; more than that
+    /// fails with [`ErrorKind::TooManyLines`].
+    ///
+    /// The offsets may be given as anything that iterates over `u32`s,
+    /// [`LineOffset`]s or references to either, such as an array, slice,
+    /// `Vec`, range or iterator chain:
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), gpio_cdev::Error> {
+    /// let mut chip = gpio_cdev::Chip::new("/dev/gpiochip0")?;
+    /// let header = chip.get_lines(&[4, 17, 27])?;
+    /// let bus = chip.get_lines(8..16)?;
+    ///
+    /// let offsets: Vec<u32> = vec![5, 6, 13];
+    /// let by_ref = chip.get_lines(&offsets)?;
+    /// let by_slice = chip.get_lines(&offsets[1..])?;
+    /// let odd = chip.get_lines(offsets.iter().filter(|&&o| o % 2 == 1))?;
+    /// let by_value = chip.get_lines(offsets)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`LineOffset`]: struct.LineOffset.html
+    /// [`ErrorKind::TooManyLines`]: errors/enum.ErrorKind.html#variant.TooManyLines
+    pub fn get_lines<I>(&mut self, offsets: I) -> Result<Lines>
+    where
+        I: IntoIterator,
+        I::Item: Into<LineOffset>,
+    {
         Lines::new(self.inner.clone(), offsets)
     }
 
+    /// Check that a request for `offsets` would be accepted, without making it
+    ///
+    /// This runs the checks the request methods make before issuing the
+    /// ioctl, plus a stricter check on the consumer label, so that a
+    /// configuration can be validated up front: each offset must exist on
+    /// the chip and be given only once, between 1 and 64 lines must be
+    /// given, `flags` must pass [`LineRequestFlags::validate`], and
+    /// `consumer` must fit the kernel's 31 byte label without being
+    /// truncated.
+    ///
+    /// The kernel may still refuse the request, e.g. because a line is in
+    /// use or the driver does not support the flags.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::Offset`], [`ErrorKind::InvalidOffsets`] for
+    /// a repeated offset, which the kernel refuses with `EBUSY`,
+    /// [`ErrorKind::NoLines`], [`ErrorKind::TooManyLines`],
+    /// [`ErrorKind::ConsumerTooLong`] or the errors of
+    /// [`LineRequestFlags::validate`].
+    ///
+    /// [`LineRequestFlags::validate`]: struct.LineRequestFlags.html#method.validate
+    /// [`ErrorKind::Offset`]: errors/enum.ErrorKind.html#variant.Offset
+    /// [`ErrorKind::InvalidOffsets`]: errors/enum.ErrorKind.html#variant.InvalidOffsets
+    /// [`ErrorKind::NoLines`]: errors/enum.ErrorKind.html#variant.NoLines
+    /// [`ErrorKind::TooManyLines`]: errors/enum.ErrorKind.html#variant.TooManyLines
+    /// [`ErrorKind::ConsumerTooLong`]: errors/enum.ErrorKind.html#variant.ConsumerTooLong
+    pub fn validate_request<I>(
+        &self,
+        offsets: I,
+        flags: LineRequestFlags,
+        consumer: &str,
+    ) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Into<LineOffset>,
+    {
+        let lines = Lines::new(self.inner.clone(), offsets)?;
+        if lines.is_empty() {
+            return Err(no_lines_err());
+        }
+        let offsets = lines.offsets();
+        for (i, offset) in offsets.iter().enumerate() {
+            if offsets[..i].contains(offset) {
+                return Err(invalid_offsets_err(format!(
+                    "offset {} is given more than once",
+                    offset
+                )));
+            }
+        }
+        flags.validate()?;
+        // The label is copied into a 32 byte buffer including the NUL
+        if consumer.len() > CONSUMER_LABEL_MAX {
+            return Err(consumer_too_long_err(consumer.len()));
+        }
+        Ok(())
+    }
+
+    /// Get a handle to the GPIO lines whose offsets are set in `mask`
+    ///
+    /// Bit `i` of `mask` selects the line at offset `i`, so only the
+    /// first 64 lines of a chip can be selected this way.  The lines are
+    /// ordered by offset.  This is the inverse of [`Lines::to_mask`].
+    ///
+    /// [`Lines::to_mask`]: struct.Lines.html#method.to_mask
+    pub fn get_lines_from_mask(&mut self, mask: u64) -> Result<Lines> {
+        self.get_lines((0..64).filter(|i| mask & (1 << i) != 0))
+    }
+
     /// Get a handle to all the GPIO lines on the chip
     ///
-    /// The group of lines can be manipulated simultaneously.
+    /// The group of lines can be manipulated simultaneously.  This fails
+    /// for chips with more than 64 lines, as for [`get_lines`].
+    ///
+    /// [`get_lines`]: #method.get_lines
     pub fn get_all_lines(&mut self) -> Result<Lines> {
-        let offsets: Vec<u32> = (0..self.num_lines()).collect();
-        self.get_lines(&offsets)
+        let num_lines = self.num_lines();
+        self.get_lines(0..num_lines)
+    }
+
+    /// Check whether the kernel accepts a request with `flags`
+    ///
+    /// This makes a request with `flags` for the first unclaimed input
+    /// line on the chip and immediately releases it, reporting whether the
+    /// kernel rejected the flags with `EINVAL`.  If `flags` includes no
+    /// direction then `INPUT` is added.
+    ///
+    /// Only the kernel's own checks are detected, so this tells whether
+    /// the running kernel knows the flags, e.g. the `BIAS_*` flags from
+    /// Linux v5.5, and not whether the driver implements them.  The kernel
+    /// ignores drivers without bias support, so a bias request succeeds on
+    /// them and the bias is simply not applied.
+    ///
+    /// The probe applies `flags` to the line it picks, and the kernel does
+    /// not undo a bias on release, so probing a `BIAS_*` flag leaves that
+    /// bias on the first unclaimed input line.
+    ///
+    /// Returns `None` if every line is claimed or configured as an output,
+    /// leaving no line to probe.  The result of a probe is cached, so each
+    /// combination of flags is only probed once per chip.
+    ///
+    /// # Errors
+    ///
+    /// Flags including `OUTPUT` are refused with
+    /// [`ErrorKind::OutputProbe`], as probing them would drive a line.
+    /// Flags that are invalid as an input request, e.g. `OPEN_DRAIN`, fail
+    /// as for [`LineRequestFlags::validate`].
+    ///
+    /// [`ErrorKind::OutputProbe`]: enum.ErrorKind.html#variant.OutputProbe
+    /// [`LineRequestFlags::validate`]: struct.LineRequestFlags.html#method.validate
+    pub fn supports(&self, flags: LineRequestFlags) -> Result<Option<bool>> {
+        let mut flags = flags;
+        if flags.contains(LineRequestFlags::OUTPUT) {
+            return Err(output_probe_err(flags));
+        }
+        flags |= LineRequestFlags::INPUT;
+        flags.validate()?;
+        if let Some(&supported) = self
+            .inner
+            .supported_flags
+            .lock()
+            .unwrap()
+            .get(&flags.bits())
+        {
+            return Ok(Some(supported));
+        }
+
+        // Only unclaimed inputs are probed, so the request neither fails
+        // with EBUSY nor changes the direction of the line
+        let offset = match self
+            .lines_filtered(|info| info.is_available() && info.direction() == LineDirection::In)
+            .next()
+        {
+            Some(res) => res?.0,
+            None => return Ok(None),
+        };
+        let supported = match Line::new(self.inner.clone(), offset)?.request(flags, 0, "probe") {
+            Ok(_) => true,
+            Err(e) if e.is_invalid() => false,
+            Err(e) => return Err(e),
+        };
+        self.inner
+            .supported_flags
+            .lock()
+            .unwrap()
+            .insert(flags.bits(), supported);
+        Ok(Some(supported))
+    }
+
+    /// Read the info of every line on the chip in one pass
+    ///
+    /// The returned snapshot answers queries without further syscalls, so
+    /// it suits tools that display or search the state of a whole chip.
+    /// It is not updated when lines change; take a new snapshot, or watch
+    /// the lines with [`watch_all`], to follow changes.
+    ///
+    /// [`watch_all`]: #method.watch_all
+    pub fn snapshot(&self) -> Result<ChipSnapshot> {
+        Ok(ChipSnapshot {
+            path: self.inner.path.clone(),
+            name: self.inner.name.clone(),
+            label: self.inner.label.clone(),
+            lines: self
+                .lines()
+                .map(|line| line.info())
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Report which optional features of the uAPI the running kernel has
+    ///
+    /// This is based only on the kernel version, so a feature reported as
+    /// available may still be rejected by the GPIO driver.  Use
+    /// [`probe_capabilities`] to also check driver support.  If the kernel
+    /// version cannot be determined every feature is assumed available.
+    ///
+    /// [`probe_capabilities`]: #method.probe_capabilities
+    pub fn capabilities(&self) -> ChipCapabilities {
+        let version = kernel_version();
+        let at_least = |min| match version {
+            Some(version) => version >= min,
+            None => true,
+        };
+        ChipCapabilities {
+            bias: at_least((5, 5)),
+            set_config: at_least((5, 5)),
+            watch_line_info: at_least((5, 7)),
+            event_clock: EventClock::for_running_kernel(),
+        }
+    }
+
+    /// Like [`capabilities`], but also check that the kernel accepts the
+    /// bias flags
+    ///
+    /// Bias support is checked with [`supports`], which briefly requests an
+    /// unclaimed input line on the chip with each bias setting.  This
+    /// confirms the kernel knows the flags where its version could not be
+    /// determined, but cannot tell whether the driver applies a bias; see
+    /// [`supports`].  Like [`supports`], this may leave a bias setting
+    /// on the probed line.  If there is no line to probe, the result of
+    /// [`capabilities`] is kept.
+    ///
+    /// [`capabilities`]: #method.capabilities
+    /// [`supports`]: #method.supports
+    pub fn probe_capabilities(&self) -> Result<ChipCapabilities> {
+        let mut caps = self.capabilities();
+        if caps.bias {
+            for &bias in [
+                LineRequestFlags::BIAS_PULL_UP,
+                LineRequestFlags::BIAS_PULL_DOWN,
+                LineRequestFlags::BIAS_DISABLE,
+            ]
+            .iter()
+            {
+                if self.supports(LineRequestFlags::INPUT | bias)? == Some(false) {
+                    caps.bias = false;
+                    break;
+                }
+            }
+        }
+        Ok(caps)
+    }
+
+    /// Start watching a line for changes to its info
+    ///
+    /// Returns the current info for the line.  The kernel then queues a
+    /// change event on the chip whenever the line is requested, released
+    /// or reconfigured, until [`unwatch_line_info`] is called or the chip
+    /// is closed.  Watching a line that is already watched fails with
+    /// `EBUSY`.
+    ///
+    /// Requires Linux v5.7 or later.
+    ///
+    /// [`unwatch_line_info`]: #method.unwatch_line_info
+    pub fn watch_line_info<O: Into<LineOffset>>(&self, offset: O) -> Result<LineInfo> {
+        let offset = offset.into().0;
+        let line = Line::new(self.inner.clone(), offset)?;
+        let mut line_info = ffi::gpioline_info {
+            line_offset: offset,
+            flags: 0,
+            name: [0; 32],
+            consumer: [0; 32],
+        };
+        unsafe { ffi::gpio_get_lineinfo_watch_ioctl(self.inner.file.as_raw_fd(), &mut line_info) }?;
+        Ok(LineInfo::new(line, &line_info))
+    }
+
+    /// Stop watching a line previously watched with [`watch_line_info`]
+    ///
+    /// [`watch_line_info`]: #method.watch_line_info
+    pub fn unwatch_line_info<O: Into<LineOffset>>(&self, offset: O) -> Result<()> {
+        let mut offset = offset.into().0;
+        unsafe { ffi::gpio_get_lineinfo_unwatch_ioctl(self.inner.file.as_raw_fd(), &mut offset) }?;
+        Ok(())
+    }
+
+    /// Start watching every line of the chip for changes to its info
+    ///
+    /// The kernel only supports watching lines one at a time, so this
+    /// issues a watch for each offset in turn.  Lines that are already
+    /// being watched are left as they are.  Use [`info_changes`] to read
+    /// the resulting change events.
+    ///
+    /// Requires Linux v5.7 or later.
+    ///
+    /// [`info_changes`]: #method.info_changes
+    pub fn watch_all(&self) -> Result<()> {
+        for offset in 0..self.num_lines() {
+            match self.watch_line_info(offset) {
+                Err(e) if e.is_busy() => {}
+                res => {
+                    res?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Block until the line at `offset` is released by its current user
+    ///
+    /// Returns `true` once the line is free, immediately if it is not in
+    /// use, or `false` if `timeout` expires first.  This watches the line
+    /// for info changes for the duration of the call, so a contended line
+    /// can be requested as soon as it is released instead of polling.
+    ///
+    /// Changes to other lines watched on this chip that arrive while
+    /// waiting are consumed and discarded.
+    ///
+    /// Requires Linux v5.7 or later.
+    pub fn wait_for_release<O: Into<LineOffset>>(
+        &self,
+        offset: O,
+        timeout: Option<Duration>,
+    ) -> Result<bool> {
+        let offset = offset.into().0;
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let (info, watched) = match self.watch_line_info(offset) {
+            Ok(info) => (info, true),
+            Err(e) if e.is_busy() => (Line::new(self.inner.clone(), offset)?.info()?, false),
+            Err(e) => return Err(e),
+        };
+        let released = if info.is_kernel() {
+            self.wait_for_released_change(offset, deadline)
+        } else {
+            Ok(true)
+        };
+        if watched {
+            self.unwatch_line_info(offset)?;
+        }
+        released
+    }
+
+    fn wait_for_released_change(&self, offset: u32, deadline: Option<Instant>) -> Result<bool> {
+        let mut changes = self.info_changes();
+        loop {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if !wait_for_readable(&self.inner.file, remaining)? {
+                return Ok(false);
+            }
+            match changes.read_change()? {
+                Some(change)
+                    if change.info().line().offset() == offset
+                        && change.kind() == LineInfoChangeKind::Released =>
+                {
+                    return Ok(true)
+                }
+                Some(_) => {}
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Get an iterator over changes to the info of watched lines
+    ///
+    /// Each call to `next` blocks until the kernel reports that a line
+    /// watched with [`watch_line_info`] or [`watch_all`] was requested,
+    /// released or reconfigured.  Changes for all watched lines on the
+    /// chip are reported through the one iterator.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), gpio_cdev::Error> {
+    /// let chip = gpio_cdev::Chip::new("/dev/gpiochip0")?;
+    /// chip.watch_all()?;
+    /// for change in chip.info_changes() {
+    ///     let change = change?;
+    ///     println!("{:?}: {:?}", change.kind(), change.info());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`watch_line_info`]: #method.watch_line_info
+    /// [`watch_all`]: #method.watch_all
+    pub fn info_changes(&self) -> LineInfoChanges {
+        LineInfoChanges {
+            chip: self.inner.clone(),
+        }
     }
 
     /// Get an interator over all lines that can be potentially access for this
@@ -292,6 +818,55 @@ impl Chip {
             idx: 0,
         }
     }
+
+    /// Get an iterator over the lines of this chip whose info matches `f`
+    ///
+    /// The info for each line is requested from the kernel in turn and
+    /// passed to the predicate.  Failures to get the info are yielded
+    /// as errors without consulting the predicate.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), gpio_cdev::Error> {
+    /// use gpio_cdev::{Chip, LineDirection};
+    ///
+    /// let chip = Chip::new("/dev/gpiochip0")?;
+    /// let outputs = chip.lines_filtered(|info| {
+    ///     info.direction() == LineDirection::Out && info.is_active_low()
+    /// });
+    /// for line in outputs {
+    ///     let (offset, info) = line?;
+    ///     println!("{}: {:?}", offset, info.consumer());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lines_filtered<F>(&self, f: F) -> impl Iterator<Item = Result<(u32, LineInfo)>>
+    where
+        F: Fn(&LineInfo) -> bool,
+    {
+        self.lines().filter_map(move |line| match line.info() {
+            Ok(info) if f(&info) => Some(Ok((line.offset(), info))),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// List the lines on this chip that are in use, with their consumers
+    ///
+    /// Returns the offset and consumer label of each line in use, whether
+    /// by userspace or by a kernel driver.  Lines in use without a
+    /// consumer label are listed with an empty string.  This is useful to
+    /// find out who holds a line when a request fails with `EBUSY`.
+    pub fn consumers(&self) -> Result<Vec<(u32, String)>> {
+        self.lines_filtered(LineInfo::is_kernel)
+            .map(|line| {
+                let (offset, info) = line?;
+                Ok((offset, info.consumer().unwrap_or_default().to_owned()))
+            })
+            .collect()
+    }
 }
 
 /// Iterator over GPIO Lines for a given chip.
@@ -316,6 +891,58 @@ impl Iterator for LineIterator {
     }
 }
 
+/// The offset of a line within its chip
+///
+/// Methods that take an offset accept anything convertible into a
+/// `LineOffset`, including a plain `u32`, so the newtype can be used to
+/// keep offsets apart from values and indices without making literal
+/// offsets any less convenient:
+///
+/// ```no_run
+/// # fn main() -> Result<(), gpio_cdev::Error> {
+/// use gpio_cdev::{Chip, LineOffset};
+///
+/// const LED: LineOffset = LineOffset(17);
+///
+/// let mut chip = Chip::new("/dev/gpiochip0")?;
+/// let led = chip.get_line(LED)?;
+/// let button = chip.get_line(4)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LineOffset(pub u32);
+
+impl From<u32> for LineOffset {
+    fn from(offset: u32) -> Self {
+        LineOffset(offset)
+    }
+}
+
+impl<'a> From<&'a u32> for LineOffset {
+    fn from(offset: &'a u32) -> Self {
+        LineOffset(*offset)
+    }
+}
+
+impl<'a> From<&'a LineOffset> for LineOffset {
+    fn from(offset: &'a LineOffset) -> Self {
+        *offset
+    }
+}
+
+impl From<LineOffset> for u32 {
+    fn from(offset: LineOffset) -> Self {
+        offset.0
+    }
+}
+
+impl std::fmt::Display for LineOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// Access to a specific GPIO Line
 ///
 /// GPIO Lines must be obtained through a parent [`Chip`] and
@@ -348,6 +975,9 @@ bitflags! {
     ///
     /// Maps to kernel [`GPIOHANDLE_REQUEST_*`] flags.
     ///
+    /// If none of the `BIAS_*` flags are set the kernel leaves the line's
+    /// bias as it is, which is not the same as requesting `BIAS_DISABLE`.
+    ///
     /// [`GPIOHANDLE_REQUEST_*`]: https://elixir.bootlin.com/linux/v4.9.127/source/include/uapi/linux/gpio.h#L58
     pub struct LineRequestFlags: u32 {
         const INPUT = (1 << 0);
@@ -355,6 +985,12 @@ bitflags! {
         const ACTIVE_LOW = (1 << 2);
         const OPEN_DRAIN = (1 << 3);
         const OPEN_SOURCE = (1 << 4);
+        /// Requires Linux v5.5 or later
+        const BIAS_PULL_UP = (1 << 5);
+        /// Requires Linux v5.5 or later
+        const BIAS_PULL_DOWN = (1 << 6);
+        /// Requires Linux v5.5 or later
+        const BIAS_DISABLE = (1 << 7);
     }
 }
 
@@ -383,54 +1019,475 @@ bitflags! {
         const ACTIVE_LOW = (1 << 2);
         const OPEN_DRAIN = (1 << 3);
         const OPEN_SOURCE = (1 << 4);
+        const BIAS_PULL_UP = (1 << 5);
+        const BIAS_PULL_DOWN = (1 << 6);
+        const BIAS_DISABLE = (1 << 7);
     }
 }
 
-/// In or Out
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum LineDirection {
-    In,
-    Out,
+impl LineRequestFlags {
+    /// Check that the kernel would accept this combination of flags
+    ///
+    /// This mirrors the checks the kernel makes when a line is requested or
+    /// reconfigured, so that an invalid combination is reported with the
+    /// flags at fault rather than as a bare `EINVAL` from the ioctl.  The
+    /// request and configuration methods in this crate run this check
+    /// before issuing any ioctl.
+    ///
+    /// Passing validation does not guarantee that the kernel or driver
+    /// supports the flags; see [`Chip::supports`] for that.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::ConflictingFlags`] if the flags include both
+    /// `INPUT` and `OUTPUT`, both `OPEN_DRAIN` and `OPEN_SOURCE`, or more
+    /// than one of the `BIAS_*` flags.  Fails with
+    /// [`ErrorKind::MissingFlags`] if `OPEN_DRAIN` or `OPEN_SOURCE` is set
+    /// without `OUTPUT`, or a `BIAS_*` flag is set without a direction.
+    ///
+    /// ```
+    /// use gpio_cdev::LineRequestFlags;
+    ///
+    /// assert!((LineRequestFlags::OUTPUT | LineRequestFlags::OPEN_DRAIN).validate().is_ok());
+    /// assert!((LineRequestFlags::INPUT | LineRequestFlags::OPEN_DRAIN).validate().is_err());
+    /// ```
+    ///
+    /// [`Chip::supports`]: struct.Chip.html#method.supports
+    /// [`ErrorKind::ConflictingFlags`]: errors/enum.ErrorKind.html#variant.ConflictingFlags
+    /// [`ErrorKind::MissingFlags`]: errors/enum.ErrorKind.html#variant.MissingFlags
+    pub fn validate(&self) -> Result<()> {
+        let direction = Self::INPUT | Self::OUTPUT;
+        let drive = Self::OPEN_DRAIN | Self::OPEN_SOURCE;
+        let bias = Self::BIAS_PULL_UP | Self::BIAS_PULL_DOWN | Self::BIAS_DISABLE;
+        for &group in [direction, drive].iter() {
+            if self.contains(group) {
+                return Err(conflicting_flags_err(group));
+            }
+        }
+        if (*self & bias).bits().count_ones() > 1 {
+            return Err(conflicting_flags_err(*self & bias));
+        }
+        if self.intersects(drive) && !self.contains(Self::OUTPUT) {
+            return Err(missing_flags_err(*self & drive, Self::OUTPUT));
+        }
+        if self.intersects(bias) && !self.intersects(direction) {
+            return Err(missing_flags_err(*self & bias, direction));
+        }
+        Ok(())
+    }
 }
 
-unsafe fn cstrbuf_to_string(buf: &[libc::c_char]) -> Option<String> {
-    if buf[0] == 0 {
-        None
-    } else {
-        Some(CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned())
+impl LineFlags {
+    /// Construct from the flags reported by the kernel, keeping any bits
+    /// this crate does not know about
+    fn from_kernel(bits: u32) -> Self {
+        // Unknown bits are only ever carried along and reported, never
+        // interpreted, which bitflags permits.
+        unsafe { Self::from_bits_unchecked(bits) }
+    }
+
+    /// Bits reported by the kernel that this crate does not know about
+    ///
+    /// Newer kernels may report flags that postdate this crate.  These are
+    /// preserved rather than discarded so they can be detected and logged.
+    pub fn unknown_bits(&self) -> u32 {
+        self.bits() & !Self::all().bits()
     }
 }
 
-impl Line {
-    fn new(chip: Arc<InnerChip>, offset: u32) -> Result<Self> {
-        if offset >= chip.lines {
-            return Err(offset_err(offset));
+/// Convert the flags reported for a line into request flags that would
+/// configure a line the same way
+///
+/// The `KERNEL` flag only reports that the line is in use and is dropped.
+/// The direction is taken from `IS_OUT`, so the result always includes
+/// either `INPUT` or `OUTPUT`.
+///
+/// ```
+/// use gpio_cdev::{LineFlags, LineRequestFlags};
+/// use std::convert::TryFrom;
+///
+/// let flags = LineFlags::KERNEL | LineFlags::IS_OUT | LineFlags::OPEN_DRAIN;
+/// assert_eq!(
+///     LineRequestFlags::try_from(flags).unwrap(),
+///     LineRequestFlags::OUTPUT | LineRequestFlags::OPEN_DRAIN
+/// );
+/// assert!(LineRequestFlags::try_from(LineFlags::OPEN_DRAIN).is_err());
+/// ```
+///
+/// Fails with [`ErrorKind::UnrepresentableFlags`] for flags unknown to this
+/// crate, both open drain and open source, open drain or open source on an
+/// input, or more than one bias setting.
+///
+/// [`ErrorKind::UnrepresentableFlags`]: errors/enum.ErrorKind.html#variant.UnrepresentableFlags
+impl TryFrom<LineFlags> for LineRequestFlags {
+    type Error = Error;
+
+    fn try_from(flags: LineFlags) -> Result<Self> {
+        let bias = LineFlags::BIAS_PULL_UP | LineFlags::BIAS_PULL_DOWN | LineFlags::BIAS_DISABLE;
+        if flags.unknown_bits() != 0
+            || flags.contains(LineFlags::OPEN_DRAIN | LineFlags::OPEN_SOURCE)
+            || (flags.intersects(LineFlags::OPEN_DRAIN | LineFlags::OPEN_SOURCE)
+                && !flags.contains(LineFlags::IS_OUT))
+            || (flags & bias).bits().count_ones() > 1
+        {
+            return Err(unrepresentable_err(flags));
         }
-        Ok(Self { chip, offset })
-    }
 
-    /// Get info about the line from the kernel.
-    pub fn info(&self) -> Result<LineInfo> {
-        let mut line_info = ffi::gpioline_info {
-            line_offset: self.offset,
-            flags: 0,
-            name: [0; 32],
-            consumer: [0; 32],
+        let mut request = if flags.contains(LineFlags::IS_OUT) {
+            LineRequestFlags::OUTPUT
+        } else {
+            LineRequestFlags::INPUT
         };
-        ffi::gpio_get_lineinfo_ioctl(self.chip.file.as_raw_fd(), &mut line_info)?;
-
-        Ok(LineInfo {
-            line: self.clone(),
-            flags: LineFlags::from_bits_truncate(line_info.flags),
-            name: unsafe { cstrbuf_to_string(&line_info.name[..]) },
-            consumer: unsafe { cstrbuf_to_string(&line_info.consumer[..]) },
-        })
+        let mapping = [
+            (LineFlags::ACTIVE_LOW, LineRequestFlags::ACTIVE_LOW),
+            (LineFlags::OPEN_DRAIN, LineRequestFlags::OPEN_DRAIN),
+            (LineFlags::OPEN_SOURCE, LineRequestFlags::OPEN_SOURCE),
+            (LineFlags::BIAS_PULL_UP, LineRequestFlags::BIAS_PULL_UP),
+            (LineFlags::BIAS_PULL_DOWN, LineRequestFlags::BIAS_PULL_DOWN),
+            (LineFlags::BIAS_DISABLE, LineRequestFlags::BIAS_DISABLE),
+        ];
+        for &(line_flag, request_flag) in mapping.iter() {
+            if flags.contains(line_flag) {
+                request |= request_flag;
+            }
+        }
+        Ok(request)
     }
+}
 
-    /// Offset of this line within its parent chip
-    pub fn offset(&self) -> u32 {
-        self.offset
-    }
+/// Spellings accepted when parsing [`LineRequestFlags`], following the
+/// options of the libgpiod tools where they have an equivalent
+const LINE_REQUEST_FLAG_NAMES: &[(&str, LineRequestFlags)] = &[
+    ("input", LineRequestFlags::INPUT),
+    ("output", LineRequestFlags::OUTPUT),
+    ("active-low", LineRequestFlags::ACTIVE_LOW),
+    ("push-pull", LineRequestFlags::empty()),
+    ("open-drain", LineRequestFlags::OPEN_DRAIN),
+    ("open-source", LineRequestFlags::OPEN_SOURCE),
+    ("as-is", LineRequestFlags::empty()),
+    ("pull-up", LineRequestFlags::BIAS_PULL_UP),
+    ("pull-down", LineRequestFlags::BIAS_PULL_DOWN),
+    ("disable", LineRequestFlags::BIAS_DISABLE),
+];
+
+/// Spellings accepted when parsing [`EventRequestFlags`]
+const EVENT_REQUEST_FLAG_NAMES: &[(&str, EventRequestFlags)] = &[
+    ("both", EventRequestFlags::BOTH_EDGES),
+    ("rising", EventRequestFlags::RISING_EDGE),
+    ("falling", EventRequestFlags::FALLING_EDGE),
+];
+
+/// Parse a comma-separated list of names from `table`, combining their flags
+fn parse_flags<T>(s: &str, empty: T, table: &'static [(&'static str, T)]) -> Result<T>
+where
+    T: Copy + BitOr<Output = T>,
+{
+    let mut flags = empty;
+    for token in s.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        match table
+            .iter()
+            .find(|&&(name, _)| name.eq_ignore_ascii_case(token))
+        {
+            Some(&(_, flag)) => flags = flags | flag,
+            None => {
+                let expected = table.iter().map(|&(name, _)| name).collect();
+                return Err(unknown_token_err(token, expected));
+            }
+        }
+    }
+    Ok(flags)
+}
+
+/// Write the names from `table` that make up `flags`, separated by commas
+fn fmt_flags<T>(
+    f: &mut std::fmt::Formatter,
+    flags: T,
+    empty: T,
+    table: &[(&str, T)],
+) -> std::fmt::Result
+where
+    T: Copy + PartialEq + BitAnd<Output = T> + Sub<Output = T>,
+{
+    let mut remaining = flags;
+    let mut sep = "";
+    for &(name, flag) in table {
+        if flag != empty && remaining & flag == flag {
+            write!(f, "{}{}", sep, name)?;
+            remaining = remaining - flag;
+            sep = ",";
+        }
+    }
+    Ok(())
+}
+
+/// Parse a comma-separated list of flags, e.g. `"input,pull-up,active-low"`
+///
+/// The names match the options of the libgpiod tools: `input`, `output`,
+/// `active-low`, `open-drain`, `open-source`, `pull-up`, `pull-down` and
+/// `disable` (for `BIAS_DISABLE`).  `push-pull` and `as-is` are accepted
+/// and set no flags.  Names are case-insensitive and an empty string
+/// parses as no flags.
+///
+/// Parsing does not check that the flags make sense together; see
+/// [`LineRequestFlags::validate`].
+///
+/// ```
+/// use gpio_cdev::LineRequestFlags;
+///
+/// let flags: LineRequestFlags = "output,open-drain".parse().unwrap();
+/// assert_eq!(flags, LineRequestFlags::OUTPUT | LineRequestFlags::OPEN_DRAIN);
+/// assert!("output,pull-sideways".parse::<LineRequestFlags>().is_err());
+/// ```
+///
+/// [`LineRequestFlags::validate`]: struct.LineRequestFlags.html#method.validate
+impl FromStr for LineRequestFlags {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_flags(s, Self::empty(), LINE_REQUEST_FLAG_NAMES)
+    }
+}
+
+/// Formats as the comma-separated list accepted by `FromStr`
+///
+/// ```
+/// use gpio_cdev::LineRequestFlags;
+///
+/// let flags = LineRequestFlags::INPUT | LineRequestFlags::BIAS_PULL_UP;
+/// assert_eq!(flags.to_string(), "input,pull-up");
+/// assert_eq!(flags.to_string().parse::<LineRequestFlags>().unwrap(), flags);
+/// ```
+impl std::fmt::Display for LineRequestFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt_flags(f, *self, Self::empty(), LINE_REQUEST_FLAG_NAMES)
+    }
+}
+
+/// Parse a comma-separated list of edges: `rising`, `falling` or `both`
+impl FromStr for EventRequestFlags {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_flags(s, Self::empty(), EVENT_REQUEST_FLAG_NAMES)
+    }
+}
+
+/// Formats as the comma-separated list accepted by `FromStr`
+impl std::fmt::Display for EventRequestFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt_flags(f, *self, Self::empty(), EVENT_REQUEST_FLAG_NAMES)
+    }
+}
+
+/// Optional uAPI features available for a chip, see [`Chip::capabilities`]
+///
+/// [`Chip::capabilities`]: struct.Chip.html#method.capabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChipCapabilities {
+    bias: bool,
+    set_config: bool,
+    watch_line_info: bool,
+    event_clock: EventClock,
+}
+
+impl ChipCapabilities {
+    /// Whether the `BIAS_*` request flags are supported (Linux v5.5)
+    pub fn bias(&self) -> bool {
+        self.bias
+    }
+
+    /// Whether requested lines can be reconfigured with `set_config`
+    /// (Linux v5.5)
+    pub fn set_config(&self) -> bool {
+        self.set_config
+    }
+
+    /// Whether lines can be watched for info changes (Linux v5.7)
+    pub fn watch_line_info(&self) -> bool {
+        self.watch_line_info
+    }
+
+    /// The clock used to timestamp line events
+    pub fn event_clock(&self) -> EventClock {
+        self.event_clock
+    }
+}
+
+/// The info of a chip and all its lines at one point in time
+///
+/// Created with [`Chip::snapshot`].
+///
+/// [`Chip::snapshot`]: struct.Chip.html#method.snapshot
+#[derive(Debug, Clone)]
+pub struct ChipSnapshot {
+    path: PathBuf,
+    name: String,
+    label: String,
+    lines: Vec<LineInfo>,
+}
+
+impl ChipSnapshot {
+    /// The fs path of the chip, as for [`Chip::path`]
+    ///
+    /// [`Chip::path`]: struct.Chip.html#method.path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The name of the chip, as for [`Chip::name`]
+    ///
+    /// [`Chip::name`]: struct.Chip.html#method.name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The label of the chip, as for [`Chip::label`]
+    ///
+    /// [`Chip::label`]: struct.Chip.html#method.label
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The number of lines on the chip
+    pub fn num_lines(&self) -> u32 {
+        self.lines.len() as u32
+    }
+
+    /// The info of the line at `offset`, or `None` if there is no such line
+    pub fn line<O: Into<LineOffset>>(&self, offset: O) -> Option<&LineInfo> {
+        self.lines.get(offset.into().0 as usize)
+    }
+
+    /// The info of the first line named `name`, if any
+    pub fn find_by_name(&self, name: &str) -> Option<&LineInfo> {
+        self.lines.iter().find(|info| info.name() == Some(name))
+    }
+
+    /// Iterate over the info of every line, in offset order
+    pub fn iter(&self) -> slice::Iter<'_, LineInfo> {
+        self.lines.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ChipSnapshot {
+    type Item = &'a LineInfo;
+    type IntoIter = slice::Iter<'a, LineInfo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lines.iter()
+    }
+}
+
+/// In or Out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineDirection {
+    In,
+    Out,
+}
+
+/// Bias applied to a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bias {
+    PullUp,
+    PullDown,
+    Disabled,
+}
+
+/// How an output line is driven
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Drive {
+    PushPull,
+    OpenDrain,
+    OpenSource,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LineDirection {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            LineDirection::In => serializer.serialize_unit_variant("LineDirection", 0, "input"),
+            LineDirection::Out => serializer.serialize_unit_variant("LineDirection", 1, "output"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Bias {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Bias::PullUp => serializer.serialize_unit_variant("Bias", 0, "pull-up"),
+            Bias::PullDown => serializer.serialize_unit_variant("Bias", 1, "pull-down"),
+            Bias::Disabled => serializer.serialize_unit_variant("Bias", 2, "disabled"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Drive {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Drive::PushPull => serializer.serialize_unit_variant("Drive", 0, "push-pull"),
+            Drive::OpenDrain => serializer.serialize_unit_variant("Drive", 1, "open-drain"),
+            Drive::OpenSource => serializer.serialize_unit_variant("Drive", 2, "open-source"),
+        }
+    }
+}
+
+/// Major and minor version of the running kernel, if it can be determined
+fn kernel_version() -> Option<(u32, u32)> {
+    let uts = nix::sys::utsname::uname();
+    let mut parts = uts
+        .release()
+        .split(|c: char| !c.is_ascii_digit())
+        .map(str::parse::<u32>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => Some((major, minor)),
+        _ => None,
+    }
+}
+
+unsafe fn cstrbuf_to_string(buf: &[libc::c_char]) -> Option<String> {
+    if buf[0] == 0 {
+        None
+    } else {
+        Some(CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned())
+    }
+}
+
+impl Line {
+    fn new(chip: Arc<InnerChip>, offset: u32) -> Result<Self> {
+        if offset >= chip.lines {
+            return Err(offset_err(offset));
+        }
+        Ok(Self { chip, offset })
+    }
+
+    /// Get info about the line from the kernel.
+    pub fn info(&self) -> Result<LineInfo> {
+        let mut line_info = ffi::gpioline_info {
+            line_offset: self.offset,
+            flags: 0,
+            name: [0; 32],
+            consumer: [0; 32],
+        };
+        unsafe { ffi::gpio_get_lineinfo_ioctl(self.chip.file.as_raw_fd(), &mut line_info) }?;
+
+        Ok(LineInfo::new(self.clone(), &line_info))
+    }
+
+    /// Offset of this line within its parent chip
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
 
     /// Get a handle to this chip's parent
     pub fn chip(&self) -> Chip {
@@ -451,6 +1508,13 @@ impl Line {
     /// `consumer` string should describe the process consuming the
     /// line (this will be truncated to 31 characters if too long).
     ///
+    /// If `flags` contains neither `INPUT` nor `OUTPUT` the line is
+    /// requested "as-is": it is claimed and its value can be read, but
+    /// its direction is left unchanged.  This allows observing a line
+    /// configured by firmware or a bootloader without disturbing it.
+    /// Setting the value of such a line fails with `EPERM` unless it was
+    /// already an output.
+    ///
     /// # Errors
     ///
     /// The main source of errors here is if the kernel returns an
@@ -472,31 +1536,36 @@ impl Line {
     ) -> Result<LineHandle> {
         // prepare the request; the kernel consumes some of these values and will
         // set the fd for us.
-        let mut request = ffi::gpiohandle_request {
-            lineoffsets: unsafe { mem::zeroed() },
-            flags: flags.bits(),
-            default_values: unsafe { mem::zeroed() },
-            consumer_label: unsafe { mem::zeroed() },
-            lines: 1,
-            fd: 0,
-        };
-        request.lineoffsets[0] = self.offset;
-        request.default_values[0] = default;
-        unsafe {
-            rstr_lcpy(
-                request.consumer_label[..].as_mut_ptr(),
-                consumer,
-                request.consumer_label.len(),
-            );
-        }
-        ffi::gpio_get_linehandle_ioctl(self.chip.file.as_raw_fd(), &mut request)?;
+        flags.validate()?;
+        let mut request = ffi::gpiohandle_request::new(&[self.offset], flags, &[default], consumer);
+        unsafe { ffi::gpio_get_linehandle_ioctl(self.chip.file.as_raw_fd(), &mut request) }?;
         Ok(LineHandle {
             line: self.clone(),
             flags,
+            consumer: consumer.to_owned(),
             file: unsafe { File::from_raw_fd(request.fd) },
         })
     }
 
+    /// Request the line, retrying while it is busy
+    ///
+    /// Behaves like [`request`], but if the kernel reports that the line
+    /// is in use by another consumer (`EBUSY`) the request is retried
+    /// after sleeping for `delay`.  At most `attempts` requests are made;
+    /// if the line is still busy after the last one its error is returned.
+    ///
+    /// [`request`]: #method.request
+    pub fn request_retry(
+        &self,
+        flags: LineRequestFlags,
+        default: u8,
+        consumer: &str,
+        attempts: usize,
+        delay: Duration,
+    ) -> Result<LineHandle> {
+        retry_busy(attempts, delay, || self.request(flags, default, consumer))
+    }
+
     /// Get an event handle that can be used as a blocking iterator over
     /// the events (state changes) for this Line
     ///
@@ -540,6 +1609,7 @@ impl Line {
         event_flags: EventRequestFlags,
         consumer: &str,
     ) -> Result<LineEventHandle> {
+        handle_flags.validate()?;
         let mut request = ffi::gpioevent_request {
             lineoffset: self.offset,
             handleflags: handle_flags.bits(),
@@ -555,7 +1625,7 @@ impl Line {
                 request.consumer_label.len(),
             );
         }
-        ffi::gpio_get_lineevent_ioctl(self.chip.file.as_raw_fd(), &mut request)?;
+        unsafe { ffi::gpio_get_lineevent_ioctl(self.chip.file.as_raw_fd(), &mut request) }?;
 
         unsafe {
             let flags = libc::fcntl(request.fd, libc::F_GETFL, 0);
@@ -565,6 +1635,13 @@ impl Line {
         Ok(LineEventHandle {
             line: self.clone(),
             file: unsafe { File::from_raw_fd(request.fd) },
+            clock: EventClock::for_running_kernel(),
+            // Only 32-bit x86 userspace may see either record size
+            record_size: if cfg!(target_arch = "x86") {
+                None
+            } else {
+                Some(ffi::GPIOEVENT_DATA_SIZE_MAX)
+            },
         })
     }
 
@@ -577,28 +1654,68 @@ impl Line {
         consumer: &str,
     ) -> Result<AsyncLineEventHandle> {
         let events = self.events(handle_flags, event_flags, consumer)?;
-        Ok(AsyncLineEventHandle::new(events)?)
+        AsyncLineEventHandle::new(events)
     }
 }
 
 impl LineInfo {
+    fn new(line: Line, line_info: &ffi::gpioline_info) -> Self {
+        LineInfo {
+            line,
+            flags: LineFlags::from_kernel(line_info.flags),
+            name: unsafe { cstrbuf_to_string(&line_info.name[..]) },
+            consumer: unsafe { cstrbuf_to_string(&line_info.consumer[..]) },
+        }
+    }
+
     /// Get a handle to the line that this info represents
     pub fn line(&self) -> &Line {
         &self.line
     }
 
-    /// Name assigned to this chip if assigned
+    /// The name of this GPIO line, such as the output pin of the line on the
+    /// chip, a rail or a pin header name on a board, as specified by the gpio
+    /// chip.
     pub fn name(&self) -> Option<&str> {
         self.name.as_deref()
     }
 
-    /// The name of this GPIO line, such as the output pin of the line on the
-    /// chip, a rail or a pin header name on a board, as specified by the gpio
-    /// chip.
+    /// A functional name for the consumer of this GPIO line as set by
+    /// whatever is using it, or `None` if the line is not in use or the
+    /// consumer did not set a label.
     pub fn consumer(&self) -> Option<&str> {
         self.consumer.as_deref()
     }
 
+    /// The flags reported by the kernel for this line
+    ///
+    /// This includes any flags unknown to this crate; see
+    /// [`LineFlags::unknown_bits`].
+    ///
+    /// [`LineFlags::unknown_bits`]: struct.LineFlags.html#method.unknown_bits
+    pub fn flags(&self) -> LineFlags {
+        self.flags
+    }
+
+    /// The request flags that would configure a line as this one is now
+    ///
+    /// This allows a line to be re-requested the way it is currently
+    /// configured, e.g. after it was transiently released.  The `KERNEL`
+    /// flag only reports that the line is in use and is not carried over.
+    ///
+    /// This is shorthand for `LineRequestFlags::try_from(info.flags())`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::UnrepresentableFlags`] if the line reports
+    /// flags unknown to this crate, both open drain and open source, open
+    /// drain or open source on an input, or more than one bias setting.
+    ///
+    /// [`ErrorKind::UnrepresentableFlags`]: errors/enum.ErrorKind.html#variant.UnrepresentableFlags
+    pub fn request_flags(&self) -> Result<LineRequestFlags> {
+        LineRequestFlags::try_from(self.flags)
+    }
+
     /// Get the direction of this GPIO if configured
     ///
     /// Lines are considered to be inputs if not explicitly
@@ -611,6 +1728,67 @@ impl LineInfo {
         }
     }
 
+    /// Get the bias of this GPIO if the kernel reports one
+    ///
+    /// Returns `None` if no bias flag is set, which means the bias is
+    /// whatever the hardware defaults to; this is not the same as
+    /// `Bias::Disabled`.  Reporting bias requires Linux v5.5 or later.
+    pub fn bias(&self) -> Option<Bias> {
+        if self.flags.contains(LineFlags::BIAS_PULL_UP) {
+            Some(Bias::PullUp)
+        } else if self.flags.contains(LineFlags::BIAS_PULL_DOWN) {
+            Some(Bias::PullDown)
+        } else if self.flags.contains(LineFlags::BIAS_DISABLE) {
+            Some(Bias::Disabled)
+        } else {
+            None
+        }
+    }
+
+    /// Get the drive of this GPIO, or `None` if it is not an output
+    pub fn drive(&self) -> Option<Drive> {
+        if !self.flags.contains(LineFlags::IS_OUT) {
+            None
+        } else if self.flags.contains(LineFlags::OPEN_DRAIN) {
+            Some(Drive::OpenDrain)
+        } else if self.flags.contains(LineFlags::OPEN_SOURCE) {
+            Some(Drive::OpenSource)
+        } else {
+            Some(Drive::PushPull)
+        }
+    }
+
+    /// Compare this info with a later snapshot of the same line
+    ///
+    /// Lists which properties differ from this info to `other`, e.g. to
+    /// log changes to a line's configuration over time.
+    pub fn diff(&self, other: &LineInfo) -> LineInfoDiff {
+        let checks = [
+            (LineInfoField::Name, self.name != other.name),
+            (LineInfoField::Consumer, self.consumer != other.consumer),
+            (LineInfoField::Used, self.is_kernel() != other.is_kernel()),
+            (
+                LineInfoField::Direction,
+                self.direction() != other.direction(),
+            ),
+            (
+                LineInfoField::ActiveLow,
+                self.is_active_low() != other.is_active_low(),
+            ),
+            (LineInfoField::Drive, self.drive() != other.drive()),
+            (LineInfoField::Bias, self.bias() != other.bias()),
+        ];
+        LineInfoDiff {
+            before: self.clone(),
+            after: other.clone(),
+            fields: checks
+                .iter()
+                .filter(|&&(_, changed)| changed)
+                .map(|&(field, _)| field)
+                .collect(),
+        }
+    }
+
     /// True if the any flags for the device are set (input or output)
     pub fn is_used(&self) -> bool {
         !self.flags.is_empty()
@@ -626,6 +1804,32 @@ impl LineInfo {
         self.flags.contains(LineFlags::KERNEL)
     }
 
+    /// True if the line was not in use when this info was read
+    ///
+    /// This is the negation of [`is_kernel`], which despite its name is set
+    /// for lines held by kernel drivers and by other processes alike.  The
+    /// line may be requested by someone else between reading the info and
+    /// requesting it, so a request can still fail with `EBUSY`; see
+    /// [`Line::request_retry`] for a way to cope with that.
+    ///
+    /// [`is_kernel`]: #method.is_kernel
+    /// [`Line::request_retry`]: struct.Line.html#method.request_retry
+    pub fn is_available(&self) -> bool {
+        !self.is_kernel()
+    }
+
+    /// The consumer holding the line, if it is in use
+    ///
+    /// Returns `None` if the line is available.  A line in use without a
+    /// consumer label gives `Some("")`.
+    pub fn used_by(&self) -> Option<&str> {
+        if self.is_kernel() {
+            Some(self.consumer().unwrap_or_default())
+        } else {
+            None
+        }
+    }
+
     /// True if this line is marked as active low in the kernel
     pub fn is_active_low(&self) -> bool {
         self.flags.contains(LineFlags::ACTIVE_LOW)
@@ -642,61 +1846,391 @@ impl LineInfo {
     }
 }
 
-/// Handle for interacting with a "requested" line
+impl std::fmt::Display for LineInfo {
+    /// Formats the info in the layout used by `lsgpio`
+    ///
+    /// For example `line  17: BUTTON1 my-app [used output active-low]`.
+    /// Lines without a name or consumer show `unnamed` and `unused`, and
+    /// the bracketed flags are omitted if there are none.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "line {:>3}: {} {}",
+            self.line.offset(),
+            self.name().unwrap_or("unnamed"),
+            self.consumer().unwrap_or("unused")
+        )?;
+
+        let mut flags = vec![];
+        if self.is_kernel() {
+            flags.push("used");
+        }
+        if self.direction() == LineDirection::Out {
+            flags.push("output");
+        }
+        if self.is_active_low() {
+            flags.push("active-low");
+        }
+        match self.drive() {
+            Some(Drive::OpenDrain) => flags.push("open-drain"),
+            Some(Drive::OpenSource) => flags.push("open-source"),
+            _ => {}
+        }
+        match self.bias() {
+            Some(Bias::PullUp) => flags.push("pull-up"),
+            Some(Bias::PullDown) => flags.push("pull-down"),
+            Some(Bias::Disabled) => flags.push("bias-disabled"),
+            None => {}
+        }
+        if !flags.is_empty() {
+            write!(f, " [{}]", flags.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Two infos are equal if they are for the same line, as identified by
+/// the chip path and offset, and report the same flags, name and consumer
+impl PartialEq for LineInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.line.offset == other.line.offset
+            && self.line.chip.path == other.line.chip.path
+            && self.flags == other.flags
+            && self.name == other.name
+            && self.consumer == other.consumer
+    }
+}
+
+impl Eq for LineInfo {}
+
+/// A property of a line that can differ between two [`LineInfo`]s
 ///
-/// In order for userspace to read/write the value of a GPIO
-/// it must be requested from the chip using [`Line::request`].
-/// On success, the kernel creates an anonymous file descriptor
-/// for interacting with the requested line.  This structure
-/// is the go-between for callers and that file descriptor.
+/// [`LineInfo`]: struct.LineInfo.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineInfoField {
+    Name,
+    Consumer,
+    Used,
+    Direction,
+    ActiveLow,
+    Drive,
+    Bias,
+}
+
+/// The differences between two snapshots of a line, see [`LineInfo::diff`]
 ///
-/// [`Line::request`]: struct.Line.html#method.request
-#[derive(Debug)]
-pub struct LineHandle {
-    line: Line,
-    flags: LineRequestFlags,
-    file: File,
+/// Displays as a comma-separated list of the changes, e.g.
+/// `consumer: unused -> my-app, direction: In -> Out`.
+///
+/// [`LineInfo::diff`]: struct.LineInfo.html#method.diff
+#[derive(Debug, Clone)]
+pub struct LineInfoDiff {
+    before: LineInfo,
+    after: LineInfo,
+    fields: Vec<LineInfoField>,
 }
 
-impl LineHandle {
-    /// Request the current state of this Line from the kernel
-    ///
-    /// This call is expected to succeed for both input and output
-    /// lines.  It should be noted, however, that some drivers may
-    /// not be able to give any useful information when the value
-    /// is requested for an output line.
-    ///
-    /// This value should be 0 or 1 which a "1" representing that
-    /// the line is active.  Usually this means that the line is
-    /// at logic-level high but it could mean the opposite if the
-    /// line has been marked as being `ACTIVE_LOW`.
-    pub fn get_value(&self) -> Result<u8> {
-        let mut data: ffi::gpiohandle_data = unsafe { mem::zeroed() };
-        ffi::gpiohandle_get_line_values_ioctl(self.file.as_raw_fd(), &mut data)?;
-        Ok(data.values[0])
+impl LineInfoDiff {
+    /// The properties that changed
+    pub fn fields(&self) -> &[LineInfoField] {
+        &self.fields
     }
 
-    /// Request that the line be driven to the specified value
+    /// True if nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The earlier snapshot
+    pub fn before(&self) -> &LineInfo {
+        &self.before
+    }
+
+    /// The later snapshot
+    pub fn after(&self) -> &LineInfo {
+        &self.after
+    }
+}
+
+impl std::fmt::Display for LineInfoDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (old, new) = (&self.before, &self.after);
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match field {
+                LineInfoField::Name => write!(
+                    f,
+                    "name: {} -> {}",
+                    old.name().unwrap_or("unnamed"),
+                    new.name().unwrap_or("unnamed")
+                )?,
+                LineInfoField::Consumer => write!(
+                    f,
+                    "consumer: {} -> {}",
+                    old.consumer().unwrap_or("unused"),
+                    new.consumer().unwrap_or("unused")
+                )?,
+                LineInfoField::Used => {
+                    write!(f, "used: {} -> {}", old.is_kernel(), new.is_kernel())?
+                }
+                LineInfoField::Direction => write!(
+                    f,
+                    "direction: {:?} -> {:?}",
+                    old.direction(),
+                    new.direction()
+                )?,
+                LineInfoField::ActiveLow => write!(
+                    f,
+                    "active-low: {} -> {}",
+                    old.is_active_low(),
+                    new.is_active_low()
+                )?,
+                LineInfoField::Drive => write!(f, "drive: {:?} -> {:?}", old.drive(), new.drive())?,
+                LineInfoField::Bias => write!(f, "bias: {:?} -> {:?}", old.bias(), new.bias())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serializes the info as a map with the fields `offset`, `name`,
+/// `consumer`, `direction`, `active_low`, `bias`, `drive` and `used`
+///
+/// `name`, `consumer`, `bias` and `drive` are null when absent.  The
+/// direction, bias and drive use the spellings of the `FromStr` grammar
+/// for request flags, e.g. `"output"`, `"pull-up"` or `"open-drain"`.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for LineInfo {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("LineInfo", 8)?;
+        state.serialize_field("offset", &self.line.offset())?;
+        state.serialize_field("name", &self.name())?;
+        state.serialize_field("consumer", &self.consumer())?;
+        state.serialize_field("direction", &self.direction())?;
+        state.serialize_field("active_low", &self.is_active_low())?;
+        state.serialize_field("bias", &self.bias())?;
+        state.serialize_field("drive", &self.drive())?;
+        state.serialize_field("used", &self.is_kernel())?;
+        state.end()
+    }
+}
+
+/// What happened to a watched line
+///
+/// Maps to kernel [`GPIOLINE_CHANGED_*`] definitions.
+///
+/// [`GPIOLINE_CHANGED_*`]: https://elixir.bootlin.com/linux/v5.7/source/include/uapi/linux/gpio.h#L94
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineInfoChangeKind {
+    Requested,
+    Released,
+    Reconfigured,
+}
+
+/// A change to the info of a watched line
+///
+/// Wraps kernel [`struct gpioline_info_changed`].
+///
+/// [`struct gpioline_info_changed`]: https://elixir.bootlin.com/linux/v5.7/source/include/uapi/linux/gpio.h#L106
+#[derive(Debug, Clone)]
+pub struct LineInfoChange {
+    info: LineInfo,
+    timestamp: u64,
+    kind: LineInfoChangeKind,
+}
+
+impl LineInfoChange {
+    /// The info of the line after the change
+    pub fn info(&self) -> &LineInfo {
+        &self.info
+    }
+
+    /// Best estimate of the time the change occurred, in nanoseconds
+    ///
+    /// This is taken from `CLOCK_MONOTONIC`.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// What happened to the line
+    pub fn kind(&self) -> LineInfoChangeKind {
+        self.kind
+    }
+}
+
+/// Iterator over changes to watched lines, see [`Chip::info_changes`]
+///
+/// [`Chip::info_changes`]: struct.Chip.html#method.info_changes
+#[derive(Debug)]
+pub struct LineInfoChanges {
+    chip: Arc<InnerChip>,
+}
+
+impl LineInfoChanges {
+    fn read_change(&mut self) -> Result<Option<LineInfoChange>> {
+        let mut buf = [0u8; mem::size_of::<ffi::gpioline_info_changed>()];
+        let read_count = (&self.chip.file).read(&mut buf)?;
+        if read_count == 0 {
+            return Ok(None);
+        }
+        if read_count != buf.len() {
+            return Err(invalid_data_err(format!(
+                "unexpected gpioline_info_changed record size: {} bytes",
+                read_count
+            ))
+            .into());
+        }
+        let change = ffi::gpioline_info_changed::from_bytes(&buf);
+        let kind = match change.event_type {
+            ffi::GPIOLINE_CHANGED_REQUESTED => LineInfoChangeKind::Requested,
+            ffi::GPIOLINE_CHANGED_RELEASED => LineInfoChangeKind::Released,
+            ffi::GPIOLINE_CHANGED_CONFIG => LineInfoChangeKind::Reconfigured,
+            other => {
+                return Err(
+                    invalid_data_err(format!("unknown line info change type: {}", other)).into(),
+                )
+            }
+        };
+        let line = Line::new(self.chip.clone(), change.info.line_offset)?;
+        Ok(Some(LineInfoChange {
+            info: LineInfo::new(line, &change.info),
+            timestamp: change.timestamp,
+            kind,
+        }))
+    }
+}
+
+impl Iterator for LineInfoChanges {
+    type Item = Result<LineInfoChange>;
+
+    fn next(&mut self) -> Option<Result<LineInfoChange>> {
+        self.read_change().transpose()
+    }
+}
+
+/// Handle for interacting with a "requested" line
+///
+/// In order for userspace to read/write the value of a GPIO
+/// it must be requested from the chip using [`Line::request`].
+/// On success, the kernel creates an anonymous file descriptor
+/// for interacting with the requested line.  This structure
+/// is the go-between for callers and that file descriptor.
+///
+/// [`Line::request`]: struct.Line.html#method.request
+#[derive(Debug)]
+pub struct LineHandle {
+    line: Line,
+    flags: LineRequestFlags,
+    consumer: String,
+    file: File,
+}
+
+impl LineHandle {
+    /// Request the current state of this Line from the kernel
+    ///
+    /// This call is expected to succeed for both input and output
+    /// lines.  It should be noted, however, that some drivers may
+    /// not be able to give any useful information when the value
+    /// is requested for an output line.
+    ///
+    /// This value should be 0 or 1 which a "1" representing that
+    /// the line is active.  Usually this means that the line is
+    /// at logic-level high but it could mean the opposite if the
+    /// line has been marked as being `ACTIVE_LOW`.
+    pub fn get_value(&self) -> Result<u8> {
+        let mut data: ffi::gpiohandle_data = unsafe { mem::zeroed() };
+        unsafe { ffi::gpiohandle_get_line_values_ioctl(self.file.as_raw_fd(), &mut data) }?;
+        Ok(data.values[0])
+    }
+
+    /// Request that the line be driven to the specified value
     ///
     /// The value should be 0 or 1 with 1 representing a request
     /// to make the line "active".  Usually "active" means
     /// logic level high unless the line has been marked as `ACTIVE_LOW`.
     ///
     /// Calling `set_value` on a line that is not an output will
-    /// likely result in an error (from the kernel).
+    /// result in an error from the kernel (`EPERM`), including for lines
+    /// requested as-is which were not already outputs.
     pub fn set_value(&self, value: u8) -> Result<()> {
         let mut data: ffi::gpiohandle_data = unsafe { mem::zeroed() };
         data.values[0] = value;
-        ffi::gpiohandle_set_line_values_ioctl(self.file.as_raw_fd(), &mut data)?;
+        unsafe { ffi::gpiohandle_set_line_values_ioctl(self.file.as_raw_fd(), &mut data) }?;
+        Ok(())
+    }
+
+    /// Change the configuration of the line without releasing it
+    ///
+    /// The `flags` replace those the handle was requested with and, if the
+    /// line is (re)configured as an output, `default` is the value it will
+    /// be driven to.  This avoids the window in which another consumer
+    /// could claim the line between releasing and re-requesting it.
+    ///
+    /// Requires Linux v5.5 or later; older kernels fail the ioctl.
+    pub fn set_config(&mut self, flags: LineRequestFlags, default: u8) -> Result<()> {
+        set_config(&self.file, flags, &[default])?;
+        self.flags = flags;
         Ok(())
     }
 
+    /// Reconfigure the line as an input and read its value
+    ///
+    /// This is intended for bidirectional lines which are driven and then
+    /// released to sample what the other side is driving.  Flags other
+    /// than the direction and drive mode (e.g. `ACTIVE_LOW`) are kept and
+    /// the line is left configured as an input.
+    ///
+    /// The line floats from the moment it stops being driven until the
+    /// other side drives it, so a value sampled immediately may not yet
+    /// reflect the external state.
+    ///
+    /// Requires Linux v5.5 or later, as for [`set_config`].
+    ///
+    /// [`set_config`]: #method.set_config
+    pub fn sample_as_input(&mut self) -> Result<u8> {
+        self.set_config(input_flags(self.flags), 0)?;
+        self.get_value()
+    }
+
+    /// Release the line and request it again with new flags
+    ///
+    /// The line is re-requested with the same consumer label.  Unlike
+    /// [`set_config`], this performs a full release and request, so it
+    /// works for changes the kernel will not apply in place and on kernels
+    /// without `GPIOHANDLE_SET_CONFIG_IOCTL`.  Another consumer may claim
+    /// the line between the release and the new request.
+    ///
+    /// `flags` are validated before the line is released, but if the new
+    /// request fails for any other reason the original request is lost
+    /// and the line is left unclaimed.
+    ///
+    /// [`set_config`]: #method.set_config
+    pub fn reopen(self, flags: LineRequestFlags, default: u8) -> Result<LineHandle> {
+        flags.validate()?;
+        let LineHandle {
+            line,
+            consumer,
+            file,
+            ..
+        } = self;
+        drop(file);
+        line.request(flags, default, &consumer)
+    }
+
     /// Get the Line information associated with this handle.
     pub fn line(&self) -> &Line {
         &self.line
     }
 
-    /// Get the flags with which this handle was created
+    /// Get the flags with which this handle was created or last configured
     pub fn flags(&self) -> LineRequestFlags {
         self.flags
     }
@@ -709,28 +2243,49 @@ impl AsRawFd for LineHandle {
     }
 }
 
+impl IntoRawFd for LineHandle {
+    /// Consumes the handle, returning the raw file descriptor
+    ///
+    /// The caller becomes responsible for closing the descriptor, which
+    /// releases the line.  Only the descriptor is kept, not the
+    /// `LineHandle` wrapping it.
+    fn into_raw_fd(self) -> RawFd {
+        self.file.into_raw_fd()
+    }
+}
+
 /// A collection of lines that can be accesses simultaneously
 ///
 /// This is a collection of lines, all from the same GPIO chip that can
 /// all be accessed simultaneously
 #[derive(Debug)]
 pub struct Lines {
+    chip: Arc<InnerChip>,
     lines: Vec<Line>,
 }
 
 impl Lines {
-    fn new(chip: Arc<InnerChip>, offsets: &[u32]) -> Result<Self> {
+    fn new<I>(chip: Arc<InnerChip>, offsets: I) -> Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: Into<LineOffset>,
+    {
         let res: Result<Vec<Line>> = offsets
-            .iter()
-            .map(|off| Line::new(chip.clone(), *off))
+            .into_iter()
+            .map(|off| Line::new(chip.clone(), off.into().0))
             .collect();
         let lines = res?;
-        Ok(Self { lines })
+        if lines.len() > ffi::GPIOHANDLES_MAX {
+            return Err(too_many_lines_err(lines.len()));
+        }
+        Ok(Self { chip, lines })
     }
 
     /// Get a handle to the parent chip for the lines
     pub fn chip(&self) -> Chip {
-        self.lines[0].chip()
+        Chip {
+            inner: self.chip.clone(),
+        }
     }
 
     /// Get the number of lines in the collection
@@ -743,6 +2298,122 @@ impl Lines {
         self.lines.len()
     }
 
+    /// Encode the offsets of the lines as a bitmask
+    ///
+    /// Bit `i` is set if the line at offset `i` is in the collection.
+    /// The order of the lines is not preserved.  Lines can be recreated
+    /// from the mask with [`Chip::get_lines_from_mask`].
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::Offset`] if any line has an offset of 64 or
+    /// more, as it cannot be represented in the mask.
+    ///
+    /// [`Chip::get_lines_from_mask`]: struct.Chip.html#method.get_lines_from_mask
+    /// [`ErrorKind::Offset`]: errors/enum.ErrorKind.html#variant.Offset
+    pub fn to_mask(&self) -> Result<u64> {
+        self.lines
+            .iter()
+            .try_fold(0u64, |mask, line| match line.offset() {
+                offset if offset < 64 => Ok(mask | (1 << offset)),
+                offset => Err(offset_err(offset)),
+            })
+    }
+
+    /// The offsets of the lines, in request order
+    ///
+    /// These are the offsets to pass to [`Chip::get_lines`] to get the
+    /// same collection again.
+    ///
+    /// [`Chip::get_lines`]: struct.Chip.html#method.get_lines
+    pub fn offsets(&self) -> Vec<u32> {
+        self.lines.iter().map(Line::offset).collect()
+    }
+
+    /// The lines in the collection as a slice, in request order
+    pub fn as_slice(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// The first line in the collection, or `None` if it is empty
+    pub fn first(&self) -> Option<&Line> {
+        self.lines.first()
+    }
+
+    /// The last line in the collection, or `None` if it is empty
+    pub fn last(&self) -> Option<&Line> {
+        self.lines.last()
+    }
+
+    /// Keep only the lines for which `f` returns `true`
+    ///
+    /// The remaining lines keep their order.  For example, to drop the
+    /// lines that are already in use before requesting the rest:
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), gpio_cdev::Error> {
+    /// let mut chip = gpio_cdev::Chip::new("/dev/gpiochip0")?;
+    /// let mut lines = chip.get_lines(0..8)?;
+    /// lines.retain(|line| line.info().map_or(false, |info| info.is_available()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&Line) -> bool,
+    {
+        self.lines.retain(f);
+    }
+
+    /// Like [`retain`], but returns the kept lines as a new collection
+    ///
+    /// [`retain`]: #method.retain
+    pub fn filtered<F>(&self, mut f: F) -> Lines
+    where
+        F: FnMut(&Line) -> bool,
+    {
+        Lines {
+            chip: self.chip.clone(),
+            lines: self.lines.iter().filter(|line| f(line)).cloned().collect(),
+        }
+    }
+
+    /// Iterate over the lines in the collection, in request order
+    ///
+    /// `&Lines` also implements `IntoIterator`, so the lines can be
+    /// iterated over with a `for` loop:
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), gpio_cdev::Error> {
+    /// let mut chip = gpio_cdev::Chip::new("/dev/gpiochip0")?;
+    /// let lines = chip.get_lines(&[4, 17, 27])?;
+    /// for line in &lines {
+    ///     println!("{}", line.info()?);
+    /// }
+    /// let offsets: Vec<u32> = lines.iter().map(|line| line.offset()).collect();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter(&self) -> slice::Iter<'_, Line> {
+        self.lines.iter()
+    }
+
+    /// Check whether the line at `offset` is in the collection
+    pub fn contains<O: Into<LineOffset>>(&self, offset: O) -> bool {
+        self.index_of(offset).is_some()
+    }
+
+    /// Get the position of the line at `offset` within the collection
+    ///
+    /// This is the index to use with `Lines[i]` and with the values passed
+    /// to and returned from [`MultiLineHandle`].
+    ///
+    /// [`MultiLineHandle`]: struct.MultiLineHandle.html
+    pub fn index_of<O: Into<LineOffset>>(&self, offset: O) -> Option<usize> {
+        let offset = offset.into().0;
+        self.lines.iter().position(|line| line.offset() == offset)
+    }
+
     /// Request access to interact with these lines from the kernel
     ///
     /// This is similar to the "export" operation present in the sysfs
@@ -755,6 +2426,9 @@ impl Lines {
     /// `consumer` string should describe the process consuming the
     /// line (this will be truncated to 31 characters if too long).
     ///
+    /// As for [`Line::request`], `flags` without a direction request
+    /// the lines as-is.
+    ///
     /// # Errors
     ///
     /// The main source of errors here is if the kernel returns an
@@ -765,8 +2439,14 @@ impl Lines {
     /// already in use.  One can check for this prior to making the
     /// request using [`is_kernel`].
     ///
+    /// The kernel cannot request an empty set of lines, so if there are no
+    /// lines this fails with [`ErrorKind::NoLines`] without issuing the
+    /// ioctl.
+    ///
     /// [`Error`]: errors/struct.Error.html
     /// [`ErrorKind::Ioctl`]: errors/enum.ErrorKind.html#variant.Ioctl
+    /// [`ErrorKind::NoLines`]: errors/enum.ErrorKind.html#variant.NoLines
+    /// [`Line::request`]: struct.Line.html#method.request
     /// [`is_kernel`]: struct.Line.html#method.is_kernel
     pub fn request(
         &self,
@@ -775,38 +2455,64 @@ impl Lines {
         consumer: &str,
     ) -> Result<MultiLineHandle> {
         let n = self.lines.len();
+        if n == 0 {
+            return Err(no_lines_err());
+        }
         if default.len() != n {
             return Err(invalid_err(n, default.len()));
         }
+        flags.validate()?;
         // prepare the request; the kernel consumes some of these values and will
         // set the fd for us.
-        let mut request = ffi::gpiohandle_request {
-            lineoffsets: unsafe { mem::zeroed() },
-            flags: flags.bits(),
-            default_values: unsafe { mem::zeroed() },
-            consumer_label: unsafe { mem::zeroed() },
-            lines: n as u32,
-            fd: 0,
-        };
-        #[allow(clippy::needless_range_loop)] // clippy does not understand this loop correctly
-        for i in 0..n {
-            request.lineoffsets[i] = self.lines[i].offset();
-            request.default_values[i] = default[i];
-        }
-        unsafe {
-            rstr_lcpy(
-                request.consumer_label[..].as_mut_ptr(),
-                consumer,
-                request.consumer_label.len(),
-            );
-        }
-        ffi::gpio_get_linehandle_ioctl(self.lines[0].chip().inner.file.as_raw_fd(), &mut request)?;
-        let lines = self.lines.clone();
+        let offsets = self.offsets();
+        let mut request = ffi::gpiohandle_request::new(&offsets, flags, default, consumer);
+        unsafe { ffi::gpio_get_linehandle_ioctl(self.chip.file.as_raw_fd(), &mut request) }?;
         Ok(MultiLineHandle {
-            lines: Self { lines },
+            lines: Self {
+                chip: self.chip.clone(),
+                lines: self.lines.clone(),
+            },
+            flags,
+            consumer: consumer.to_owned(),
+            defaults: default.to_vec(),
             file: unsafe { File::from_raw_fd(request.fd) },
         })
     }
+
+    /// Request the lines, retrying while any of them are busy
+    ///
+    /// Behaves like [`request`], but if the kernel reports that a line
+    /// is in use by another consumer (`EBUSY`) the request is retried
+    /// after sleeping for `delay`.  At most `attempts` requests are made;
+    /// if a line is still busy after the last one its error is returned.
+    ///
+    /// [`request`]: #method.request
+    pub fn request_retry(
+        &self,
+        flags: LineRequestFlags,
+        default: &[u8],
+        consumer: &str,
+        attempts: usize,
+        delay: Duration,
+    ) -> Result<MultiLineHandle> {
+        retry_busy(attempts, delay, || self.request(flags, default, consumer))
+    }
+}
+
+/// Call `request` until it succeeds, fails with an error other than
+/// `EBUSY`, or has been tried `attempts` times (at least once)
+fn retry_busy<T, F>(attempts: usize, delay: Duration, mut request: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut remaining = attempts.max(1);
+    loop {
+        remaining -= 1;
+        match request() {
+            Err(e) if e.is_busy() && remaining > 0 => std::thread::sleep(delay),
+            res => return res,
+        }
+    }
 }
 
 impl Index<usize> for Lines {
@@ -817,6 +2523,42 @@ impl Index<usize> for Lines {
     }
 }
 
+impl std::fmt::Display for Lines {
+    /// Formats the offsets compactly, e.g. `2-7,20`
+    ///
+    /// Runs of consecutive ascending offsets are shown as ranges.  The
+    /// output can be parsed back into offsets with [`parse_offsets`].
+    ///
+    /// [`parse_offsets`]: fn.parse_offsets.html
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut offsets = self.lines.iter().map(Line::offset).peekable();
+        let mut sep = "";
+        while let Some(first) = offsets.next() {
+            let mut last = first;
+            while offsets.peek() == Some(&(last + 1)) {
+                last += 1;
+                offsets.next();
+            }
+            if first == last {
+                write!(f, "{}{}", sep, first)?;
+            } else {
+                write!(f, "{}{}-{}", sep, first, last)?;
+            }
+            sep = ",";
+        }
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a Lines {
+    type Item = &'a Line;
+    type IntoIter = slice::Iter<'a, Line>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lines.iter()
+    }
+}
+
 /// Handle for interacting with a "requested" line
 ///
 /// In order for userspace to read/write the value of a GPIO
@@ -829,6 +2571,10 @@ impl Index<usize> for Lines {
 #[derive(Debug)]
 pub struct MultiLineHandle {
     lines: Lines,
+    flags: LineRequestFlags,
+    consumer: String,
+    // The values given when the lines were requested or last reconfigured
+    defaults: Vec<u8>,
     file: File,
 }
 
@@ -846,7 +2592,7 @@ impl MultiLineHandle {
     /// line has been marked as being `ACTIVE_LOW`.
     pub fn get_values(&self) -> Result<Vec<u8>> {
         let mut data: ffi::gpiohandle_data = unsafe { mem::zeroed() };
-        ffi::gpiohandle_get_line_values_ioctl(self.file.as_raw_fd(), &mut data)?;
+        unsafe { ffi::gpiohandle_get_line_values_ioctl(self.file.as_raw_fd(), &mut data) }?;
         let n = self.num_lines();
         let values: Vec<u8> = (0..n).map(|i| data.values[i]).collect();
         Ok(values)
@@ -858,8 +2604,9 @@ impl MultiLineHandle {
     /// to make the line "active".  Usually "active" means
     /// logic level high unless the line has been marked as `ACTIVE_LOW`.
     ///
-    /// Calling `set_value` on a line that is not an output will
-    /// likely result in an error (from the kernel).
+    /// Calling `set_values` on lines that are not outputs will
+    /// result in an error from the kernel (`EPERM`), including for lines
+    /// requested as-is which were not already outputs.
     pub fn set_values(&self, values: &[u8]) -> Result<()> {
         let n = self.num_lines();
         if values.len() != n {
@@ -867,10 +2614,99 @@ impl MultiLineHandle {
         }
         let mut data: ffi::gpiohandle_data = unsafe { mem::zeroed() };
         data.values[..n].clone_from_slice(&values[..n]);
-        ffi::gpiohandle_set_line_values_ioctl(self.file.as_raw_fd(), &mut data)?;
+        unsafe { ffi::gpiohandle_set_line_values_ioctl(self.file.as_raw_fd(), &mut data) }?;
+        Ok(())
+    }
+
+    /// Set new values for the lines and return the values they had before
+    ///
+    /// This is a convenience for read-modify-write patterns that need the
+    /// previous output state.  The kernel has no atomic swap, so this is a
+    /// [`get_values`] followed by a [`set_values`]; a change made by the
+    /// kernel or hardware between the two calls is not reflected in the
+    /// returned values.
+    ///
+    /// [`get_values`]: #method.get_values
+    /// [`set_values`]: #method.set_values
+    pub fn swap_values(&self, values: &[u8]) -> Result<Vec<u8>> {
+        let n = self.num_lines();
+        if values.len() != n {
+            return Err(invalid_err(n, values.len()));
+        }
+        let previous = self.get_values()?;
+        self.set_values(values)?;
+        Ok(previous)
+    }
+
+    /// Change the configuration of the lines without releasing them
+    ///
+    /// The `flags` replace those the handle was requested with and, if the
+    /// lines are (re)configured as outputs, `default` gives the value each
+    /// line will be driven to.  This avoids the window in which another
+    /// consumer could claim the lines between releasing and re-requesting
+    /// them.
+    ///
+    /// Requires Linux v5.5 or later; older kernels fail the ioctl.
+    pub fn set_config(&mut self, flags: LineRequestFlags, default: &[u8]) -> Result<()> {
+        let n = self.num_lines();
+        if default.len() != n {
+            return Err(invalid_err(n, default.len()));
+        }
+        set_config(&self.file, flags, default)?;
+        self.flags = flags;
+        self.defaults = default.to_vec();
         Ok(())
     }
 
+    /// Reconfigure the lines as inputs and read their values
+    ///
+    /// This is intended for bidirectional lines which are driven and then
+    /// released to sample what the other side is driving.  Flags other
+    /// than the direction and drive mode (e.g. `ACTIVE_LOW`) are kept and
+    /// the lines are left configured as inputs.
+    ///
+    /// The lines float from the moment they stop being driven until the
+    /// other side drives them, so values sampled immediately may not yet
+    /// reflect the external state.
+    ///
+    /// Requires Linux v5.5 or later, as for [`set_config`].
+    ///
+    /// [`set_config`]: #method.set_config
+    pub fn sample_as_input(&mut self) -> Result<Vec<u8>> {
+        let default = vec![0; self.num_lines()];
+        self.set_config(input_flags(self.flags), &default)?;
+        self.get_values()
+    }
+
+    /// Release the lines and request them again with new flags
+    ///
+    /// The lines are re-requested with the same consumer label.  Unlike
+    /// [`set_config`], this performs a full release and request, so it
+    /// works for changes the kernel will not apply in place and on kernels
+    /// without `GPIOHANDLE_SET_CONFIG_IOCTL`.  Another consumer may claim
+    /// the lines between the release and the new request.
+    ///
+    /// `flags` and `default` are validated before the lines are released,
+    /// but if the new request fails for any other reason the original
+    /// request is lost and the lines are left unclaimed.
+    ///
+    /// [`set_config`]: #method.set_config
+    pub fn reopen(self, flags: LineRequestFlags, default: &[u8]) -> Result<MultiLineHandle> {
+        let n = self.num_lines();
+        if default.len() != n {
+            return Err(invalid_err(n, default.len()));
+        }
+        flags.validate()?;
+        let MultiLineHandle {
+            lines,
+            consumer,
+            file,
+            ..
+        } = self;
+        drop(file);
+        lines.request(flags, default, &consumer)
+    }
+
     /// Get the number of lines associated with this handle
     pub fn num_lines(&self) -> usize {
         self.lines.len()
@@ -880,6 +2716,30 @@ impl MultiLineHandle {
     pub fn lines(&self) -> &Lines {
         &self.lines
     }
+
+    /// Get the flags with which this handle was created or last configured
+    pub fn flags(&self) -> LineRequestFlags {
+        self.flags
+    }
+}
+
+/// `flags` with the direction changed to input, dropping output-only flags
+fn input_flags(flags: LineRequestFlags) -> LineRequestFlags {
+    let output_only =
+        LineRequestFlags::OUTPUT | LineRequestFlags::OPEN_DRAIN | LineRequestFlags::OPEN_SOURCE;
+    (flags - output_only) | LineRequestFlags::INPUT
+}
+
+fn set_config(file: &File, flags: LineRequestFlags, default: &[u8]) -> Result<()> {
+    flags.validate()?;
+    let mut config = ffi::gpiohandle_config {
+        flags: flags.bits(),
+        default_values: unsafe { mem::zeroed() },
+        padding: [0; 4],
+    };
+    config.default_values[..default.len()].copy_from_slice(default);
+    unsafe { ffi::gpiohandle_set_config_ioctl(file.as_raw_fd(), &mut config) }?;
+    Ok(())
 }
 
 impl AsRawFd for MultiLineHandle {
@@ -889,23 +2749,253 @@ impl AsRawFd for MultiLineHandle {
     }
 }
 
-/// Did the Line rise (go active) or fall (go inactive)?
-///
-/// Maps to kernel [`GPIOEVENT_EVENT_*`] definitions.
-///
+impl IntoRawFd for MultiLineHandle {
+    /// Consumes the handle, returning the raw file descriptor
+    ///
+    /// The caller becomes responsible for closing the descriptor, which
+    /// releases the lines.  Only the descriptor is kept, not the
+    /// `MultiLineHandle` wrapping it.
+    fn into_raw_fd(self) -> RawFd {
+        self.file.into_raw_fd()
+    }
+}
+
+/// A [`MultiLineHandle`] whose lines are addressed by name
+///
+/// This is a convenience layer for groups of lines with meaningful names,
+/// such as the data bits `D0`..`D7` of a parallel bus.  The names are
+/// either given explicitly or taken from the names the kernel reports for
+/// the lines.
+///
+/// ```no_run
+/// # fn main() -> Result<(), gpio_cdev::Error> {
+/// use gpio_cdev::{Chip, LineGroup, LineRequestFlags};
+///
+/// let mut chip = Chip::new("/dev/gpiochip0")?;
+/// let handle = chip
+///     .get_lines(&[4, 5, 6])?
+///     .request(LineRequestFlags::OUTPUT, &[0, 0, 0], "bus")?;
+/// let mut group = LineGroup::new(handle, vec![("D0", 4), ("D1", 5), ("D2", 6)])?;
+/// group.set("D1", 1)?;
+/// assert_eq!(group.get("D1")?, 1);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`MultiLineHandle`]: struct.MultiLineHandle.html
+#[derive(Debug)]
+pub struct LineGroup {
+    handle: MultiLineHandle,
+    names: HashMap<String, usize>,
+    // The values last driven onto the lines, as output lines cannot be
+    // relied upon to read back what they are driven to
+    values: Vec<u8>,
+}
+
+impl LineGroup {
+    /// Name the lines of `handle` by offset
+    ///
+    /// Lines without a name can only be accessed through [`handle`].
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::Offset`] if an offset is not one of the
+    /// lines of `handle`, and with [`ErrorKind::DuplicateName`] if a name
+    /// is given more than once.
+    ///
+    /// [`handle`]: #method.handle
+    /// [`ErrorKind::Offset`]: errors/enum.ErrorKind.html#variant.Offset
+    /// [`ErrorKind::DuplicateName`]: errors/enum.ErrorKind.html#variant.DuplicateName
+    pub fn new<S, O, I>(handle: MultiLineHandle, names: I) -> Result<Self>
+    where
+        S: Into<String>,
+        O: Into<LineOffset>,
+        I: IntoIterator<Item = (S, O)>,
+    {
+        let mut by_name = HashMap::new();
+        for (name, offset) in names {
+            let offset = offset.into();
+            let idx = match handle.lines().index_of(offset) {
+                Some(idx) => idx,
+                None => return Err(offset_err(offset.0)),
+            };
+            let name = name.into();
+            if by_name.contains_key(&name) {
+                return Err(duplicate_name_err(name));
+            }
+            by_name.insert(name, idx);
+        }
+        Ok(LineGroup::with_names(handle, by_name))
+    }
+
+    /// Name the lines of `handle` with the names reported by the kernel
+    ///
+    /// Lines the kernel reports no name for are left unnamed.  Names are
+    /// not guaranteed to be unique, e.g. several lines may be called
+    /// `"NC"`; a name shared by more than one line of `handle` is skipped,
+    /// leaving those lines unnamed.
+    pub fn from_line_names(handle: MultiLineHandle) -> Result<Self> {
+        let mut by_name = HashMap::new();
+        for idx in 0..handle.num_lines() {
+            if let Some(name) = handle.lines()[idx].info()?.name() {
+                by_name
+                    .entry(name.to_owned())
+                    .and_modify(|idx: &mut Option<usize>| *idx = None)
+                    .or_insert(Some(idx));
+            }
+        }
+        let names = by_name
+            .into_iter()
+            .filter_map(|(name, idx)| Some((name, idx?)))
+            .collect();
+        Ok(LineGroup::with_names(handle, names))
+    }
+
+    fn with_names(handle: MultiLineHandle, names: HashMap<String, usize>) -> Self {
+        let values = handle.defaults.clone();
+        LineGroup {
+            handle,
+            names,
+            values,
+        }
+    }
+
+    /// Get the value of the line called `name`
+    ///
+    /// This reads all lines of the group and picks out the one requested.
+    pub fn get(&self, name: &str) -> Result<u8> {
+        let idx = self.index_of(name)?;
+        Ok(self.handle.get_values()?[idx])
+    }
+
+    /// Set the value of the line called `name`
+    ///
+    /// The kernel sets the values of all lines in a request at once, so
+    /// this writes the values last driven onto the other lines along with
+    /// the new one.  Those start as the default values the lines were
+    /// requested or last reconfigured with, and are updated by each `set`.
+    /// They are not read back from the lines, as drivers may report the
+    /// pin level rather than the driven value, e.g. for an open drain line
+    /// held low by another device.  Values set through the underlying
+    /// handle directly are not seen.  The lines must all be outputs.
+    pub fn set(&mut self, name: &str, value: u8) -> Result<()> {
+        let idx = self.index_of(name)?;
+        let mut values = self.values.clone();
+        values[idx] = value;
+        self.handle.set_values(&values)?;
+        self.values = values;
+        Ok(())
+    }
+
+    /// The names of the lines in the group, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.keys().map(String::as_str)
+    }
+
+    /// Get the underlying handle
+    pub fn handle(&self) -> &MultiLineHandle {
+        &self.handle
+    }
+
+    /// Release the names and return the underlying handle
+    pub fn into_handle(self) -> MultiLineHandle {
+        self.handle
+    }
+
+    fn index_of(&self, name: &str) -> Result<usize> {
+        self.names
+            .get(name)
+            .copied()
+            .ok_or_else(|| unknown_line_err(name))
+    }
+}
+
+/// Did the Line rise (go active) or fall (go inactive)?
+///
+/// Maps to kernel [`GPIOEVENT_EVENT_*`] definitions.
+///
 /// [`GPIOEVENT_EVENT_*`]: https://elixir.bootlin.com/linux/v4.9.127/source/include/uapi/linux/gpio.h#L136
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum EventType {
     RisingEdge,
     FallingEdge,
 }
 
+/// Which clock the kernel used to timestamp line events
+///
+/// The v1 ABI does not let userspace choose the event clock.  Kernels
+/// before v5.7 stamp events with `CLOCK_REALTIME`; from v5.7 onwards
+/// `CLOCK_MONOTONIC` is used instead.  The clock is determined from the
+/// running kernel version when the event handle is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventClock {
+    Realtime,
+    Monotonic,
+}
+
+impl EventClock {
+    /// The clock used for v1 line events by the running kernel
+    ///
+    /// If the kernel version cannot be determined, `Monotonic` is assumed
+    /// as that is what all current kernels use.
+    fn for_running_kernel() -> Self {
+        match kernel_version() {
+            Some(version) if version < (5, 7) => EventClock::Realtime,
+            _ => EventClock::Monotonic,
+        }
+    }
+}
+
+/// Parse `realtime` or `monotonic`
+impl FromStr for EventClock {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            t if t.eq_ignore_ascii_case("realtime") => Ok(EventClock::Realtime),
+            t if t.eq_ignore_ascii_case("monotonic") => Ok(EventClock::Monotonic),
+            t => Err(unknown_token_err(t, vec!["realtime", "monotonic"])),
+        }
+    }
+}
+
+impl std::fmt::Display for EventClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EventClock::Realtime => write!(f, "realtime"),
+            EventClock::Monotonic => write!(f, "monotonic"),
+        }
+    }
+}
+
 /// Information about a change to the state of a Line
 ///
 /// Wraps kernel [`struct gpioevent_data`].
 ///
 /// [`struct gpioevent_data`]: https://elixir.bootlin.com/linux/v4.9.127/source/include/uapi/linux/gpio.h#L142
-pub struct LineEvent(ffi::gpioevent_data);
+#[derive(Clone, Copy)]
+pub struct LineEvent {
+    data: ffi::gpioevent_data,
+    clock: EventClock,
+    offset: u32,
+}
+
+impl Default for LineEvent {
+    /// A placeholder event, for filling buffers passed to
+    /// [`LineEventHandle::read_events_into`]
+    ///
+    /// [`LineEventHandle::read_events_into`]: struct.LineEventHandle.html#method.read_events_into
+    fn default() -> Self {
+        LineEvent {
+            data: ffi::gpioevent_data {
+                timestamp: 0,
+                id: 0,
+            },
+            clock: EventClock::Monotonic,
+            offset: 0,
+        }
+    }
+}
 
 impl std::fmt::Debug for LineEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -918,22 +3008,80 @@ impl std::fmt::Debug for LineEvent {
     }
 }
 
+impl std::fmt::Display for LineEvent {
+    /// Formats the event as a CSV record: `timestamp,offset,edge`
+    ///
+    /// The edge is `rising` or `falling`, e.g. `1595421346405811712,17,rising`.
+    /// This suits logging events to a file, as done by `gpiomon`.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let edge = match self.event_type() {
+            EventType::RisingEdge => "rising",
+            EventType::FallingEdge => "falling",
+        };
+        write!(f, "{},{},{}", self.timestamp(), self.offset, edge)
+    }
+}
+
 impl LineEvent {
     /// Best estimate of event occurrence time, in nanoseconds
     ///
     /// In most cases, the timestamp for the event is captured
     /// in an interrupt handler so it should be very accurate.
     ///
-    /// The nanosecond timestamp value should are captured
-    /// using the `CLOCK_REALTIME` offsets in the kernel and
-    /// should be compared against `CLOCK_REALTIME` values.
+    /// The clock the timestamp was captured with depends on the
+    /// kernel version; see [`clock`] for which clock values
+    /// should be compared against.
+    ///
+    /// [`clock`]: #method.clock
     pub fn timestamp(&self) -> u64 {
-        self.0.timestamp
+        self.data.timestamp
+    }
+
+    /// The clock used by the kernel to produce [`timestamp`]
+    ///
+    /// [`timestamp`]: #method.timestamp
+    pub fn clock(&self) -> EventClock {
+        self.clock
+    }
+
+    /// Wall-clock time of the event, if the kernel stamped it with `CLOCK_REALTIME`
+    ///
+    /// Returns `None` for events stamped with `CLOCK_MONOTONIC`, as those
+    /// cannot be meaningfully converted to a `SystemTime`; use
+    /// [`monotonic_nanos`] in that case.
+    ///
+    /// [`monotonic_nanos`]: #method.monotonic_nanos
+    pub fn system_time(&self) -> Option<SystemTime> {
+        match self.clock {
+            EventClock::Realtime => Some(UNIX_EPOCH + Duration::from_nanos(self.data.timestamp)),
+            EventClock::Monotonic => None,
+        }
+    }
+
+    /// Time of the event on `CLOCK_MONOTONIC`, in nanoseconds, if the
+    /// kernel stamped it with that clock
+    ///
+    /// This is comparable with `clock_gettime(CLOCK_MONOTONIC)` and with
+    /// the timestamps of other monotonic events, e.g. to measure the time
+    /// between edges.  Returns `None` for events stamped with
+    /// `CLOCK_REALTIME`; use [`system_time`] for those.
+    ///
+    /// [`system_time`]: #method.system_time
+    pub fn monotonic_nanos(&self) -> Option<u64> {
+        match self.clock {
+            EventClock::Realtime => None,
+            EventClock::Monotonic => Some(self.data.timestamp),
+        }
+    }
+
+    /// Offset of the line the event occurred on
+    pub fn offset(&self) -> u32 {
+        self.offset
     }
 
     /// Was this a rising or a falling edge?
     pub fn event_type(&self) -> EventType {
-        if self.0.id == 0x01 {
+        if self.data.id == 0x01 {
             EventType::RisingEdge
         } else {
             EventType::FallingEdge
@@ -955,8 +3103,13 @@ impl LineEvent {
 pub struct LineEventHandle {
     line: Line,
     file: File,
+    clock: EventClock,
+    record_size: Option<usize>,
 }
 
+/// Number of events the kernel queues for each v1 event handle
+const EVENT_QUEUE_LEN: usize = 16;
+
 impl LineEventHandle {
     /// Retrieve the next event from the kernel for this line
     ///
@@ -979,7 +3132,7 @@ impl LineEventHandle {
     /// line has been marked as being `ACTIVE_LOW`.
     pub fn get_value(&self) -> Result<u8> {
         let mut data: ffi::gpiohandle_data = unsafe { mem::zeroed() };
-        ffi::gpiohandle_get_line_values_ioctl(self.file.as_raw_fd(), &mut data)?;
+        unsafe { ffi::gpiohandle_get_line_values_ioctl(self.file.as_raw_fd(), &mut data) }?;
         Ok(data.values[0])
     }
 
@@ -987,59 +3140,237 @@ impl LineEventHandle {
     pub fn line(&self) -> &Line {
         &self.line
     }
-    
-    pub fn wait_for_event(&self, duration : Option<std::time::Duration>) -> std::io::Result<bool>
-    {
-        wait_for_readable(&self.file,duration)
+
+    /// The clock used by the kernel to timestamp events on this handle
+    pub fn clock(&self) -> EventClock {
+        self.clock
     }
 
-    pub fn try_read_event(&mut self) -> std::io::Result<Option<LineEvent>>
-    {
-        let ready = wait_for_readable(&self.file,Some(std::time::Duration::ZERO))?;
-        if !ready { return Ok(None); }
+    pub fn wait_for_event(&self, duration: Option<std::time::Duration>) -> std::io::Result<bool> {
+        wait_for_readable(&self.file, duration)
+    }
+
+    pub fn try_read_event(&mut self) -> std::io::Result<Option<LineEvent>> {
+        let ready = wait_for_readable(&self.file, Some(std::time::Duration::ZERO))?;
+        if !ready {
+            return Ok(None);
+        }
 
         self.read_event()
     }
 
-    pub fn read_event_timeout(&mut self, duration : std::time::Duration) -> std::io::Result<Option<LineEvent>>
-    {
-        let ready = wait_for_readable(&self.file,Some(duration))?;
-        if !ready { return Ok(None); }
+    pub fn read_event_timeout(
+        &mut self,
+        duration: std::time::Duration,
+    ) -> std::io::Result<Option<LineEvent>> {
+        let ready = wait_for_readable(&self.file, Some(duration))?;
+        if !ready {
+            return Ok(None);
+        }
 
         self.read_event()
     }
 
+    /// Get an iterator which merges bursts of events into one summary
+    ///
+    /// Each item is produced by waiting for an event and then merging all
+    /// further events whose kernel timestamps lie within `window` of it.
+    /// The summary reports the last edge seen and how many edges there
+    /// were, which is often all that is wanted for noisy or high-rate
+    /// inputs.
+    ///
+    /// As bursts are measured with the kernel timestamps, events that
+    /// queued up while the caller was busy are split into bursts as they
+    /// occurred.  Waiting for further events stops `window` after the
+    /// first event of a burst is read.
+    pub fn coalesced(&mut self, window: Duration) -> CoalescedEvents<'_> {
+        CoalescedEvents {
+            handle: self,
+            window,
+            pending: None,
+        }
+    }
+
+    /// Get an iterator which pairs each event with the time the line
+    /// spent in its previous state
+    ///
+    /// The dwell time of a rising edge is the time since the last falling
+    /// edge, i.e. how long the line was inactive, and vice versa.  It is
+    /// `None` until an edge of the opposite kind has been seen.  This is
+    /// useful for measuring pulse widths or how long a button was held.
+    ///
+    /// Dwell times are computed from the kernel timestamps, so they are
+    /// not affected by delays in reading the events.
+    pub fn events_with_dwell(&mut self) -> DwellEvents<'_> {
+        DwellEvents {
+            handle: self,
+            edges: LastEdges::default(),
+        }
+    }
+
     /// Helper function which returns the line event if a complete event was read, Ok(None) if not
     /// enough data was read or the error returned by `read()`.
+    ///
+    /// The size of `struct gpioevent_data` differs between architectures: it
+    /// is 12 bytes where `u64` is only 4-byte aligned (e.g. i386) and 16 bytes
+    /// elsewhere.  64-bit kernels prior to v5.7 always wrote 16 byte records,
+    /// even to 32-bit compat userspace, and reject reads smaller than that.
+    /// Reading into a buffer of the larger size accepts both layouts, as the
+    /// kernel only ever returns whole records and the fields are at the same
+    /// offsets in each.
     pub(crate) fn read_event(&mut self) -> std::io::Result<Option<LineEvent>> {
-        let mut data: ffi::gpioevent_data = unsafe { mem::zeroed() };
-        let data_as_buf = unsafe {
-            slice::from_raw_parts_mut(
-                (&mut data as *mut ffi::gpioevent_data).cast(),
-                mem::size_of::<ffi::gpioevent_data>(),
-            )
-        };
+        let mut buf = [0u8; ffi::GPIOEVENT_DATA_SIZE_MAX];
 
-        let mut read_count = 0;
-        loop {
-            match self.file.read(&mut data_as_buf[read_count..])
-            {
-                Ok(read) => read_count += read,
-                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock ) => 
-                {
+        let read_count = loop {
+            match self.file.read(&mut buf) {
+                Ok(read) => break read,
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock) => {
                     wait_for_readable(&self.file, None)?;
-                },
+                }
                 Err(e) => return Err(e),
             }
+        };
 
-            if read_count >= mem::size_of::<ffi::gpioevent_data>()
-            {
+        if read_count == 0 {
+            return Ok(None);
+        }
+        match ffi::gpioevent_data::from_record(&buf[..read_count]) {
+            Some(data) => {
+                self.record_size = Some(read_count);
+                Ok(Some(LineEvent {
+                    data,
+                    clock: self.clock,
+                    offset: self.line.offset,
+                }))
+            }
+            None => Err(record_size_err(read_count)),
+        }
+    }
+
+    /// Read as many queued events as fit into `buf` with as few reads as possible
+    ///
+    /// This blocks until at least one event is available and then returns
+    /// without blocking further, giving the number of events written to
+    /// the start of `buf`.  A return of 0 means the handle was closed.
+    ///
+    /// The kernel queues at most 16 events per handle, so a buffer of that
+    /// many events can drain the queue with one read.  A larger buffer
+    /// only helps if more events arrive while it is being filled, as reads
+    /// continue for as long as events are queued; a smaller one leaves the
+    /// rest queued for the next call.  Events are decoded from a buffer on
+    /// the stack, so reusing `buf` avoids any per-call allocation:
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), gpio_cdev::Error> {
+    /// use gpio_cdev::{Chip, EventRequestFlags, LineEvent, LineRequestFlags};
+    ///
+    /// let mut chip = Chip::new("/dev/gpiochip0")?;
+    /// let mut handle = chip.get_line(17)?.events(
+    ///     LineRequestFlags::INPUT,
+    ///     EventRequestFlags::BOTH_EDGES,
+    ///     "batch-reader",
+    /// )?;
+    /// let mut events = [LineEvent::default(); 16];
+    /// loop {
+    ///     let n = handle.read_events_into(&mut events)?;
+    ///     if n == 0 {
+    ///         break;
+    ///     }
+    ///     for event in &events[..n] {
+    ///         println!("{}", event);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_events_into(&mut self, buf: &mut [LineEvent]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut filled = 0;
+        let record_size = match self.record_size {
+            Some(size) => size,
+            None => {
+                // Reading a single event determines the record size in use
+                match self.read_event()? {
+                    Some(event) => buf[0] = event,
+                    None => return Ok(0),
+                }
+                filled = 1;
+                self.record_size.unwrap_or(ffi::GPIOEVENT_DATA_SIZE_MAX)
+            }
+        };
+
+        let mut raw = [0u8; EVENT_QUEUE_LEN * ffi::GPIOEVENT_DATA_SIZE_MAX];
+        while filled < buf.len() {
+            // Only a read with nothing yet in `buf` may block
+            if filled > 0 && !wait_for_readable(&self.file, Some(Duration::ZERO))? {
                 break;
             }
+            let wanted = min(buf.len() - filled, EVENT_QUEUE_LEN) * record_size;
+            let read_count = match self.file.read(&mut raw[..wanted]) {
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    if filled > 0 {
+                        break;
+                    }
+                    wait_for_readable(&self.file, None)?;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            filled += decode_events(
+                &raw[..read_count],
+                record_size,
+                self.clock,
+                self.line.offset,
+                &mut buf[filled..],
+            )?;
+            if read_count < wanted {
+                break;
+            }
+        }
+
+        Ok(filled)
+    }
+}
+
+/// Split `raw` into records of `record_size` bytes and decode them into
+/// `events`, returning how many were written
+fn decode_events(
+    raw: &[u8],
+    record_size: usize,
+    clock: EventClock,
+    offset: u32,
+    events: &mut [LineEvent],
+) -> std::io::Result<usize> {
+    let mut count = 0;
+    for (record, event) in raw.chunks(record_size).zip(events.iter_mut()) {
+        // The kernel only ever returns whole records, so a short final
+        // chunk means the record size is not the one in use
+        let data = ffi::gpioevent_data::from_record(record)
+            .ok_or_else(|| record_size_err(record.len()))?;
+        *event = LineEvent {
+            data,
+            clock,
+            offset,
         };
-        
-        Ok(Some(LineEvent(data)))
+        count += 1;
     }
+    Ok(count)
+}
+
+fn record_size_err(size: usize) -> std::io::Error {
+    invalid_data_err(format!(
+        "unexpected gpioevent_data record size: {} bytes",
+        size
+    ))
+}
+
+fn invalid_data_err(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
 }
 
 impl AsRawFd for LineEventHandle {
@@ -1049,6 +3380,17 @@ impl AsRawFd for LineEventHandle {
     }
 }
 
+impl IntoRawFd for LineEventHandle {
+    /// Consumes the handle, returning the raw file descriptor
+    ///
+    /// The caller becomes responsible for closing the descriptor, which
+    /// releases the line.  Only the descriptor is kept, not the
+    /// `LineEventHandle` wrapping it.
+    fn into_raw_fd(self) -> RawFd {
+        self.file.into_raw_fd()
+    }
+}
+
 impl Iterator for LineEventHandle {
     type Item = Result<LineEvent>;
 
@@ -1061,15 +3403,547 @@ impl Iterator for LineEventHandle {
     }
 }
 
-fn wait_for_readable(fd : &dyn AsRawFd, timeout : Option<std::time::Duration>) -> std::result::Result<bool,std::io::Error>
-{
+/// A burst of events on a line, merged by [`LineEventHandle::coalesced`]
+///
+/// [`LineEventHandle::coalesced`]: struct.LineEventHandle.html#method.coalesced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CoalescedEvent {
+    event_type: EventType,
+    timestamp: u64,
+    count: usize,
+}
+
+impl CoalescedEvent {
+    /// The last edge seen within the window, giving the line's final state
+    pub fn event_type(&self) -> EventType {
+        self.event_type
+    }
+
+    /// The timestamp of the last event within the window
+    ///
+    /// See [`LineEvent::timestamp`] for the clock used.
+    ///
+    /// [`LineEvent::timestamp`]: struct.LineEvent.html#method.timestamp
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// The number of events merged into this one
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Iterator returned by [`LineEventHandle::coalesced`]
+///
+/// [`LineEventHandle::coalesced`]: struct.LineEventHandle.html#method.coalesced
+#[derive(Debug)]
+pub struct CoalescedEvents<'a> {
+    handle: &'a mut LineEventHandle,
+    window: Duration,
+    // The first event after the last burst, read while looking for its end
+    pending: Option<LineEvent>,
+}
+
+impl<'a> Iterator for CoalescedEvents<'a> {
+    type Item = Result<CoalescedEvent>;
+
+    fn next(&mut self) -> Option<Result<CoalescedEvent>> {
+        let first = match self.pending.take() {
+            Some(event) => event,
+            None => match self.handle.read_event() {
+                Ok(Some(event)) => event,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e.into())),
+            },
+        };
+        let deadline = Instant::now() + self.window;
+        let mut summary = CoalescedEvent {
+            event_type: first.event_type(),
+            timestamp: first.timestamp(),
+            count: 1,
+        };
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.handle.read_event_timeout(remaining) {
+                Ok(Some(event)) => {
+                    let start = first.timestamp();
+                    if !coalesce(&mut summary, start, self.window, &event) {
+                        self.pending = Some(event);
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+
+        Some(Ok(summary))
+    }
+}
+
+/// Merge `event` into `burst` if it occurred within `window` of the first
+/// event of the burst at `start`, returning whether it was merged
+fn coalesce(burst: &mut CoalescedEvent, start: u64, window: Duration, event: &LineEvent) -> bool {
+    if u128::from(event.timestamp().saturating_sub(start)) > window.as_nanos() {
+        return false;
+    }
+    burst.event_type = event.event_type();
+    burst.timestamp = event.timestamp();
+    burst.count += 1;
+    true
+}
+
+/// Iterator returned by [`LineEventHandle::events_with_dwell`]
+///
+/// [`LineEventHandle::events_with_dwell`]: struct.LineEventHandle.html#method.events_with_dwell
+#[derive(Debug)]
+pub struct DwellEvents<'a> {
+    handle: &'a mut LineEventHandle,
+    edges: LastEdges,
+}
+
+impl<'a> Iterator for DwellEvents<'a> {
+    type Item = Result<(LineEvent, Option<Duration>)>;
+
+    fn next(&mut self) -> Option<Result<(LineEvent, Option<Duration>)>> {
+        let event = match self.handle.read_event() {
+            Ok(Some(event)) => event,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let dwell = self.edges.dwell(event.event_type(), event.timestamp());
+        Some(Ok((event, dwell)))
+    }
+}
+
+/// Timestamps of the last rising and falling edges seen on a line
+#[derive(Debug, Default, Clone, Copy)]
+struct LastEdges {
+    rising: Option<u64>,
+    falling: Option<u64>,
+}
+
+impl LastEdges {
+    /// Record an edge, returning the time since the last edge of the
+    /// opposite kind
+    ///
+    /// Repeated edges of the same kind, e.g. when the other edge was
+    /// missed, keep the opposite edge as the start of the dwell.
+    fn dwell(&mut self, event_type: EventType, timestamp: u64) -> Option<Duration> {
+        let since = match event_type {
+            EventType::RisingEdge => {
+                self.rising = Some(timestamp);
+                self.falling
+            }
+            EventType::FallingEdge => {
+                self.falling = Some(timestamp);
+                self.rising
+            }
+        };
+        since.map(|since| Duration::from_nanos(timestamp.saturating_sub(since)))
+    }
+}
+
+fn wait_for_readable(
+    fd: &dyn AsRawFd,
+    timeout: Option<std::time::Duration>,
+) -> std::result::Result<bool, std::io::Error> {
     let pollfd = nix::poll::PollFd::new(fd.as_raw_fd(), nix::poll::PollFlags::POLLIN);
-    let timeout = timeout.map(|d| std::convert::TryInto::try_into(d.as_millis()).unwrap_or(i32::MAX)).unwrap_or(-1);
+    let timeout = timeout
+        .map(|d| std::convert::TryInto::try_into(d.as_millis()).unwrap_or(i32::MAX))
+        .unwrap_or(-1);
     let res = nix::poll::poll(&mut [pollfd], timeout);
-    match res
-    {
-        Ok(v) if v == 0 => Ok(false),
+    match res {
+        Ok(0) => Ok(false),
         Ok(_) => Ok(true),
-        Err(_) => Err(std::io::Error::from_raw_os_error(nix::errno::errno()))
+        Err(_) => Err(std::io::Error::from_raw_os_error(nix::errno::errno())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A chip of `lines` lines over `/dev/null`, for checks that make no ioctls
+    fn test_chip(lines: u32) -> Arc<InnerChip> {
+        Arc::new(InnerChip {
+            path: PathBuf::from("/dev/null"),
+            file: File::open("/dev/null").unwrap(),
+            name: "gpiochip0".to_owned(),
+            label: "test".to_owned(),
+            lines,
+            supported_flags: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// A handle over `/dev/null` for the lines at `offsets`, as if requested
+    /// as outputs with `defaults`
+    fn test_handle(offsets: &[u32], defaults: &[u8]) -> MultiLineHandle {
+        MultiLineHandle {
+            lines: Lines::new(test_chip(32), offsets).unwrap(),
+            flags: LineRequestFlags::OUTPUT,
+            consumer: "test".to_owned(),
+            defaults: defaults.to_vec(),
+            file: File::open("/dev/null").unwrap(),
+        }
+    }
+
+    fn line_info(offset: u32, flags: u32, name: &str, consumer: &str) -> LineInfo {
+        let mut info = ffi::gpioline_info {
+            line_offset: offset,
+            flags,
+            name: [0; 32],
+            consumer: [0; 32],
+        };
+        unsafe {
+            rstr_lcpy(info.name.as_mut_ptr(), name, info.name.len());
+            rstr_lcpy(info.consumer.as_mut_ptr(), consumer, info.consumer.len());
+        }
+        LineInfo::new(Line::new(test_chip(32), offset).unwrap(), &info)
+    }
+
+    #[test]
+    fn bias_from_line_flags() {
+        let bias = |flags: LineFlags| line_info(0, flags.bits(), "", "").bias();
+        assert_eq!(bias(LineFlags::empty()), None);
+        assert_eq!(bias(LineFlags::BIAS_PULL_UP), Some(Bias::PullUp));
+        assert_eq!(
+            bias(LineFlags::IS_OUT | LineFlags::BIAS_PULL_DOWN),
+            Some(Bias::PullDown)
+        );
+        assert_eq!(bias(LineFlags::BIAS_DISABLE), Some(Bias::Disabled));
+    }
+
+    #[test]
+    fn drive_from_line_flags() {
+        let drive = |flags: LineFlags| line_info(0, flags.bits(), "", "").drive();
+        assert_eq!(drive(LineFlags::empty()), None);
+        assert_eq!(drive(LineFlags::OPEN_DRAIN), None);
+        assert_eq!(drive(LineFlags::IS_OUT), Some(Drive::PushPull));
+        assert_eq!(
+            drive(LineFlags::IS_OUT | LineFlags::OPEN_DRAIN),
+            Some(Drive::OpenDrain)
+        );
+        assert_eq!(
+            drive(LineFlags::IS_OUT | LineFlags::OPEN_SOURCE),
+            Some(Drive::OpenSource)
+        );
+    }
+
+    #[test]
+    fn display_line_info_as_lsgpio() {
+        let unused = line_info(3, 0, "", "");
+        assert_eq!(unused.to_string(), "line   3: unnamed unused");
+
+        let flags = LineFlags::KERNEL | LineFlags::IS_OUT | LineFlags::ACTIVE_LOW;
+        let used = line_info(17, flags.bits(), "BUTTON1", "my-app");
+        assert_eq!(
+            used.to_string(),
+            "line  17: BUTTON1 my-app [used output active-low]"
+        );
+
+        let flags =
+            LineFlags::KERNEL | LineFlags::IS_OUT | LineFlags::OPEN_DRAIN | LineFlags::BIAS_PULL_UP;
+        let i2c = line_info(2, flags.bits(), "SDA", "i2c");
+        assert_eq!(
+            i2c.to_string(),
+            "line   2: SDA i2c [used output open-drain pull-up]"
+        );
+    }
+
+    #[test]
+    fn diff_of_unchanged_line() {
+        let before = line_info(17, 0, "BUTTON1", "");
+        let after = line_info(17, 0, "BUTTON1", "");
+        assert_eq!(before, after);
+        let diff = before.diff(&after);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "");
+    }
+
+    #[test]
+    fn diff_of_requested_line() {
+        let before = line_info(17, 0, "BUTTON1", "");
+        let flags = LineFlags::KERNEL | LineFlags::IS_OUT | LineFlags::OPEN_DRAIN;
+        let after = line_info(17, flags.bits(), "BUTTON1", "my-app");
+        assert_ne!(before, after);
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.fields(),
+            &[
+                LineInfoField::Consumer,
+                LineInfoField::Used,
+                LineInfoField::Direction,
+                LineInfoField::Drive,
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "consumer: unused -> my-app, used: false -> true, direction: In -> Out, \
+             drive: None -> Some(OpenDrain)"
+        );
+        assert_eq!(diff.before(), &before);
+        assert_eq!(diff.after(), &after);
+    }
+
+    #[test]
+    fn diff_of_bias_and_polarity() {
+        let before = line_info(4, LineFlags::BIAS_PULL_UP.bits(), "", "");
+        let flags = LineFlags::BIAS_PULL_DOWN | LineFlags::ACTIVE_LOW;
+        let after = line_info(4, flags.bits(), "", "");
+        let diff = before.diff(&after);
+        assert_eq!(
+            diff.fields(),
+            &[LineInfoField::ActiveLow, LineInfoField::Bias]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "active-low: false -> true, bias: Some(PullUp) -> Some(PullDown)"
+        );
+    }
+
+    #[test]
+    fn line_group_names() {
+        let handle = test_handle(&[4, 5, 6], &[0, 1, 0]);
+        let group = LineGroup::new(handle, vec![("D0", 4), ("D2", 6)]).unwrap();
+        assert_eq!(group.index_of("D0").unwrap(), 0);
+        assert_eq!(group.index_of("D2").unwrap(), 2);
+        let mut names: Vec<_> = group.names().collect();
+        names.sort_unstable();
+        assert_eq!(names, ["D0", "D2"]);
+        match group.index_of("D1").unwrap_err().kind() {
+            ErrorKind::UnknownLine(name) => assert_eq!(name, "D1"),
+            kind => panic!("unexpected error {:?}", kind),
+        }
+        assert_eq!(group.values, [0, 1, 0]);
+    }
+
+    #[test]
+    fn line_group_accepts_line_offsets() {
+        let handle = test_handle(&[4, 5], &[0, 0]);
+        let group = LineGroup::new(handle, vec![("D0", LineOffset(5))]).unwrap();
+        assert_eq!(group.index_of("D0").unwrap(), 1);
+    }
+
+    #[test]
+    fn line_group_rejects_unknown_offset() {
+        let handle = test_handle(&[4, 5], &[0, 0]);
+        match LineGroup::new(handle, vec![("D0", 4), ("D1", 7)])
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::Offset(offset) => assert_eq!(*offset, 7),
+            kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn line_group_rejects_duplicate_names() {
+        let handle = test_handle(&[4, 5], &[0, 0]);
+        match LineGroup::new(handle, vec![("D0", 4), ("D0", 5)])
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::DuplicateName(name) => assert_eq!(name, "D0"),
+            kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn validate_request_accepts_valid_request() {
+        let chip = Chip {
+            inner: test_chip(32),
+        };
+        let check = |offsets: &[u32], flags, consumer: &str| {
+            chip.validate_request(offsets, flags, consumer)
+        };
+        let output = LineRequestFlags::OUTPUT | LineRequestFlags::OPEN_DRAIN;
+        check(&[4, 5, 31], output, "my-app").unwrap();
+        check(&[4], LineRequestFlags::INPUT, &"x".repeat(31)).unwrap();
+    }
+
+    #[test]
+    fn validate_request_rejects_bad_requests() {
+        let chip = Chip {
+            inner: test_chip(32),
+        };
+        let check = |offsets: &[u32], flags, consumer: &str| {
+            let err = chip.validate_request(offsets, flags, consumer).unwrap_err();
+            format!("{:?}", err.kind())
+        };
+        let input = LineRequestFlags::INPUT;
+
+        assert_eq!(check(&[4, 32], input, "app"), "Offset(32)");
+        assert_eq!(check(&[], input, "app"), "NoLines");
+        assert!(check(&[4, 5, 4], input, "app").starts_with("InvalidOffsets"));
+        let open_drain_input = input | LineRequestFlags::OPEN_DRAIN;
+        assert!(check(&[4], open_drain_input, "app").starts_with("MissingFlags"));
+        let both = input | LineRequestFlags::OUTPUT;
+        assert!(check(&[4], both, "app").starts_with("ConflictingFlags"));
+        assert_eq!(check(&[4], input, &"x".repeat(32)), "ConsumerTooLong(32)");
+    }
+
+    #[test]
+    fn unknown_line_flags_are_kept() {
+        let info = line_info(3, LineFlags::IS_OUT.bits() | 1 << 30, "", "");
+        assert_eq!(info.flags().unknown_bits(), 1 << 30);
+        assert!(info.flags().contains(LineFlags::IS_OUT));
+        match info.request_flags().unwrap_err().kind() {
+            ErrorKind::UnrepresentableFlags(flags) => assert_eq!(flags.unknown_bits(), 1 << 30),
+            kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+
+    fn event(timestamp: u64, id: u32, clock: EventClock) -> LineEvent {
+        LineEvent {
+            data: ffi::gpioevent_data { timestamp, id },
+            clock,
+            offset: 17,
+        }
+    }
+
+    fn raw_events(events: &[(u64, u32)], record_size: usize) -> Vec<u8> {
+        let mut raw = Vec::new();
+        for &(timestamp, id) in events {
+            raw.extend_from_slice(&timestamp.to_ne_bytes());
+            raw.extend_from_slice(&id.to_ne_bytes());
+            raw.resize(raw.len() + record_size - 12, 0);
+        }
+        raw
+    }
+
+    #[test]
+    fn decode_events_from_one_read() {
+        let queued = [(100, 1), (200, 2), (300, 1)];
+        for &record_size in &[
+            ffi::GPIOEVENT_DATA_SIZE_PACKED,
+            ffi::GPIOEVENT_DATA_SIZE_MAX,
+        ] {
+            let raw = raw_events(&queued, record_size);
+            let mut events = [LineEvent::default(); 4];
+            let n =
+                decode_events(&raw, record_size, EventClock::Realtime, 17, &mut events).unwrap();
+            assert_eq!(n, 3);
+            for (event, &(timestamp, id)) in events.iter().zip(queued.iter()) {
+                assert_eq!(event.timestamp(), timestamp);
+                assert_eq!(event.data.id, id);
+                assert_eq!(event.offset(), 17);
+                assert_eq!(event.clock(), EventClock::Realtime);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_events_rejects_partial_records() {
+        let raw = raw_events(&[(100, 1), (200, 2)], ffi::GPIOEVENT_DATA_SIZE_PACKED);
+        let mut events = [LineEvent::default(); 2];
+        let err = decode_events(
+            &raw,
+            ffi::GPIOEVENT_DATA_SIZE_MAX,
+            EventClock::Realtime,
+            17,
+            &mut events,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn display_event_as_csv() {
+        let rising = event(1_595_421_346_405_811_712, 1, EventClock::Realtime);
+        assert_eq!(rising.to_string(), "1595421346405811712,17,rising");
+        let falling = event(42, 2, EventClock::Monotonic);
+        assert_eq!(falling.to_string(), "42,17,falling");
+    }
+
+    #[test]
+    fn coalesce_by_timestamp() {
+        let window = Duration::from_millis(1);
+        let first = event(1_000_000, 1, EventClock::Monotonic);
+        let mut burst = CoalescedEvent {
+            event_type: first.event_type(),
+            timestamp: first.timestamp(),
+            count: 1,
+        };
+        let start = first.timestamp();
+        for &(timestamp, id) in &[(1_200_000, 2), (1_600_000, 1), (2_000_000, 2)] {
+            assert!(coalesce(
+                &mut burst,
+                start,
+                window,
+                &event(timestamp, id, EventClock::Monotonic)
+            ));
+        }
+        assert_eq!(burst.count(), 4);
+        assert_eq!(burst.event_type(), EventType::FallingEdge);
+        assert_eq!(burst.timestamp(), 2_000_000);
+
+        // Queued long after the burst, so it starts the next one
+        let late = event(300_000_000, 1, EventClock::Monotonic);
+        assert!(!coalesce(&mut burst, start, window, &late));
+        assert_eq!(burst.count(), 4);
+        assert_eq!(burst.timestamp(), 2_000_000);
+    }
+
+    #[test]
+    fn dwell_between_opposite_edges() {
+        let mut edges = LastEdges::default();
+        assert_eq!(edges.dwell(EventType::RisingEdge, 1_000), None);
+        assert_eq!(
+            edges.dwell(EventType::FallingEdge, 1_750),
+            Some(Duration::from_nanos(750))
+        );
+        assert_eq!(
+            edges.dwell(EventType::RisingEdge, 3_000),
+            Some(Duration::from_nanos(1_250))
+        );
+    }
+
+    #[test]
+    fn dwell_across_repeated_edges() {
+        let mut edges = LastEdges::default();
+        assert_eq!(edges.dwell(EventType::FallingEdge, 100), None);
+        assert_eq!(edges.dwell(EventType::FallingEdge, 200), None);
+        assert_eq!(
+            edges.dwell(EventType::RisingEdge, 500),
+            Some(Duration::from_nanos(300))
+        );
+        assert_eq!(
+            edges.dwell(EventType::RisingEdge, 900),
+            Some(Duration::from_nanos(700))
+        );
+        assert_eq!(
+            edges.dwell(EventType::FallingEdge, 1_000),
+            Some(Duration::from_nanos(100))
+        );
+    }
+
+    #[test]
+    fn system_time_of_realtime_event() {
+        let event = event(1_595_421_346_405_811_712, 1, EventClock::Realtime);
+        assert_eq!(
+            event.system_time(),
+            Some(UNIX_EPOCH + Duration::new(1_595_421_346, 405_811_712))
+        );
+    }
+
+    #[test]
+    fn system_time_of_monotonic_event() {
+        let event = event(123_456_789, 1, EventClock::Monotonic);
+        assert_eq!(event.system_time(), None);
+    }
+
+    #[test]
+    fn monotonic_nanos_of_monotonic_event() {
+        let event = event(123_456_789, 1, EventClock::Monotonic);
+        assert_eq!(event.monotonic_nanos(), Some(123_456_789));
+    }
+
+    #[test]
+    fn monotonic_nanos_of_realtime_event() {
+        let event = event(1_595_421_346_405_811_712, 1, EventClock::Realtime);
+        assert_eq!(event.monotonic_nanos(), None);
     }
 }